@@ -0,0 +1,186 @@
+//! Derive macro for `gsrs::DerefWithLifetime`, use it through `gsrs` with `derive` feature.
+//!
+//! Generates the same impl as `deref_with_lifetime!`, but also checks that lifetime of the struct
+//! is used only by references, so there is no owned data that borrows something else.
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, GenericArgument, GenericParam, Lifetime, PathArguments, Type,
+};
+
+/// Implements `DerefWithLifetime` for struct with single lifetime parameter.
+///
+/// Type parameters are required to be `'static`.
+#[proc_macro_derive(DerefWithLifetime)]
+pub fn derive_deref_with_lifetime(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream, Error> {
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        _ => return Err(Error::new(input.ident.span(), "`DerefWithLifetime` can only be derived for structs")),
+    };
+
+    let mut lifetimes = input.generics.lifetimes();
+    let lt = match (lifetimes.next(), lifetimes.next()) {
+        (Some(param), None) => param.lifetime.clone(),
+        _ => {
+            return Err(Error::new(
+                input.generics.span(),
+                "`DerefWithLifetime` can only be derived for structs with exactly one lifetime parameter",
+            ))
+        }
+    };
+
+    for field in data.fields.iter() {
+        check_type(&field.ty, &lt)?;
+    }
+
+    let name = &input.ident;
+    let mut params = Vec::new();
+    let mut self_args = Vec::new();
+    let mut target_args = Vec::new();
+    let mut generic = false;
+    for param in input.generics.params.iter() {
+        match param {
+            GenericParam::Lifetime(_) => {
+                self_args.push(quote!('_));
+                target_args.push(quote!('__srs));
+            }
+            GenericParam::Type(ty) => {
+                let ident = &ty.ident;
+                let bounds = &ty.bounds;
+                params.push(quote!(#ident: 'static + #bounds));
+                self_args.push(quote!(#ident));
+                target_args.push(quote!(#ident));
+                generic = true;
+            }
+            GenericParam::Const(c) => {
+                let ident = &c.ident;
+                let ty = &c.ty;
+                params.push(quote!(const #ident: #ty));
+                self_args.push(quote!(#ident));
+                target_args.push(quote!(#ident));
+            }
+        }
+    }
+    let where_clause = &input.generics.where_clause;
+
+    // size of generic struct is unknown to `transmute`, so same casts as in `deref_with_lifetime!` are used
+    let (deref, deref_mut, cast, cast_back) = if generic {
+        (
+            quote!(&*(self as *const Self as *const Self::Target)),
+            quote!(&mut *(self as *mut Self as *mut Self::Target)),
+            quote!(::gsrs::__private::cast_lifetime(self)),
+            quote!(::gsrs::__private::cast_lifetime(this)),
+        )
+    } else {
+        (
+            quote!(::core::mem::transmute(self)),
+            quote!(::core::mem::transmute(self)),
+            quote!(::core::mem::transmute(self)),
+            quote!(::core::mem::transmute(this)),
+        )
+    };
+
+    Ok(quote! {
+        unsafe impl<'__srs, #(#params,)*> ::gsrs::DerefWithLifetime<'__srs> for #name<#(#self_args),*> #where_clause {
+            type Target = #name<#(#target_args),*>;
+
+            #[inline(always)]
+            unsafe fn deref_with_lifetime(&'__srs self) -> &'__srs Self::Target {
+                #deref
+            }
+
+            #[inline(always)]
+            unsafe fn deref_with_lifetime_mut(&'__srs mut self) -> &'__srs mut Self::Target {
+                #deref_mut
+            }
+
+            #[inline(always)]
+            unsafe fn move_with_lifetime(self) -> Self::Target {
+                #cast
+            }
+
+            #[inline(always)]
+            unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+                #cast_back
+            }
+        }
+    })
+}
+
+// lifetime is allowed only as lifetime of reference, any other type with that lifetime
+// may own non-'static data which can't be checked to only borrow from `SRS` owner
+fn check_type(ty: &Type, lt: &Lifetime) -> Result<(), Error> {
+    match ty {
+        Type::Reference(r) => check_type(&r.elem, lt),
+        Type::Array(a) => check_type(&a.elem, lt),
+        Type::Slice(s) => check_type(&s.elem, lt),
+        Type::Ptr(p) => check_type(&p.elem, lt),
+        Type::Paren(p) => check_type(&p.elem, lt),
+        Type::Group(g) => check_type(&g.elem, lt),
+        Type::Tuple(t) => t.elems.iter().try_for_each(|it| check_type(it, lt)),
+        Type::Path(p) => {
+            if let Some(qself) = &p.qself {
+                check_type(&qself.ty, lt)?;
+            }
+            for segment in p.path.segments.iter() {
+                match &segment.arguments {
+                    PathArguments::None => {}
+                    PathArguments::AngleBracketed(args) => {
+                        for arg in args.args.iter() {
+                            match arg {
+                                GenericArgument::Lifetime(l) if l == lt => return Err(rejected(ty, lt)),
+                                GenericArgument::Lifetime(_) => {}
+                                GenericArgument::Type(t) => check_type(t, lt)?,
+                                GenericArgument::AssocType(assoc) => check_type(&assoc.ty, lt)?,
+                                other => check_tokens(other, ty, lt)?,
+                            }
+                        }
+                    }
+                    other => check_tokens(other, ty, lt)?,
+                }
+            }
+            Ok(())
+        }
+        other => check_tokens(other, ty, lt),
+    }
+}
+
+fn check_tokens(tokens: &impl ToTokens, ty: &Type, lt: &Lifetime) -> Result<(), Error> {
+    if mentions(tokens.to_token_stream(), lt) {
+        Err(rejected(ty, lt))
+    } else {
+        Ok(())
+    }
+}
+
+fn mentions(tokens: TokenStream, lt: &Lifetime) -> bool {
+    let mut quote = false;
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Punct(p) => {
+            quote = p.as_char() == '\'';
+            false
+        }
+        TokenTree::Ident(ident) => std::mem::replace(&mut quote, false) && ident == lt.ident,
+        TokenTree::Group(group) => {
+            quote = false;
+            mentions(group.stream(), lt)
+        }
+        TokenTree::Literal(_) => {
+            quote = false;
+            false
+        }
+    })
+}
+
+fn rejected(ty: &Type, lt: &Lifetime) -> Error {
+    Error::new(
+        ty.span(),
+        format!("`{}` can only be used as a lifetime of references, this type may own non-'static data", lt),
+    )
+}
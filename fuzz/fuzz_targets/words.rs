@@ -0,0 +1,10 @@
+#![no_main]
+use gsrs::SRS;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data).into_owned();
+    let srs = SRS::<_, Vec<&'static str>>::create_with(text, |owner| owner.split_whitespace().collect());
+    let srs = Box::new(srs);
+    srs.check_invariants(|user| user.iter().map(|it| (it.as_ptr(), it.len())).collect());
+});
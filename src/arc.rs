@@ -0,0 +1,92 @@
+use crate::DerefWithLifetime;
+use alloc::sync::Arc;
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+
+/// `SRS` with owner kept alive by `Arc`, so several of them can share one owner.
+///
+/// `Arc` already keeps owner at a stable address, so it is stored directly without extra box.
+/// Builder is a plain `fn` so it is guaranteed to not capture anything,
+/// it is kept to rebuild referencing part when `ArcSRS` is cloned.
+pub struct ArcSRS<Owner: ?Sized, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    user: ManuallyDrop<U>,
+    owner: Arc<Owner>,
+    build: for<'b> fn(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+}
+
+impl<Owner: ?Sized, U> ArcSRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates `ArcSRS` over shared `owner`, referencing part is created by `build`
+    pub fn from_arc(owner: Arc<Owner>, build: for<'b> fn(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target) -> Self {
+        // moving `Arc` doesn't move or assert unique access to the owner, so references stay valid
+        let user = unsafe { <U as DerefWithLifetime>::move_with_lifetime_back(build(owner.deref())) };
+        Self {
+            user: ManuallyDrop::new(user),
+            owner,
+            build,
+        }
+    }
+
+    /// Shared owner, clone it to create more views over the same owner
+    pub fn arc(&self) -> &Arc<Owner> {
+        &self.owner
+    }
+
+    /// Same as `SRS::with`
+    #[inline]
+    pub fn with<'b, F, Z: 'static>(&'b mut self, f: F) -> Z
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Z,
+    {
+        let user = unsafe { self.user.deref_with_lifetime_mut() };
+        f(user, self.owner.deref())
+    }
+
+    /// Same as `SRS::get_ref`
+    #[inline]
+    pub fn get_ref<'b, F, Z: ?Sized + 'static>(&'b self, f: F) -> &'b Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b Z,
+    {
+        let user = unsafe { self.user.deref_with_lifetime() };
+        f(user, self.owner.deref())
+    }
+}
+
+/// Shares the owner and rebuilds referencing part for it
+impl<Owner: ?Sized, U> Clone for ArcSRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn clone(&self) -> Self {
+        Self::from_arc(self.owner.clone(), self.build)
+    }
+}
+
+impl<Owner: ?Sized, U> Deref for ArcSRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    type Target = Owner;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.owner.deref()
+    }
+}
+
+// references have to be destroyed before the data they point into,
+// so `user` is dropped explicitly and owner is dropped afterwards as a field
+impl<Owner: ?Sized, U> Drop for ArcSRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.user) }
+    }
+}
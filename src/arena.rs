@@ -0,0 +1,26 @@
+//! Helpers for `SRS` owning `typed_arena::Arena`, which can be extended while its items are referenced.
+use crate::{DerefWithLifetime, SRS};
+use core::ops::Deref;
+use typed_arena::Arena;
+
+/// `SRS` with arena owner, referencing part usually keeps references to allocated items
+pub type ArenaSRS<T, U> = SRS<Arena<T>, U>;
+
+impl<T, U> SRS<Arena<T>, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Allocates `value` in the arena and passes reference to it to `store`,
+    /// which should save it in the referencing part.
+    ///
+    /// Same as `with` with `arena.alloc` inside, `store` is `'static` for the same reasons.
+    pub fn push<'b, F>(&'b mut self, value: T, store: F) -> &'b T
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b T),
+    {
+        let user = unsafe { self.user.deref_with_lifetime_mut() };
+        let item = &*self.owner.deref().alloc(value);
+        store(user, item);
+        item
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{DerefWithLifetime, SRS};
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+/// Gives direct access to both parts of `SRS` without closure per access, created by `SRS::refs`.
+///
+/// Guard is available only inside of the closure passed to `refs`, so references with `'g` lifetime
+/// can't outlive `SRS`. Referencing part itself is borrowed only as long as the guard value,
+/// so reference to it can't be put inside of it even if it has interior mutability.
+pub struct RefsGuard<'g, Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    srs: &'g SRS<Owner, U>,
+    _invariant: PhantomData<fn(&'g ()) -> &'g ()>,
+}
+
+impl<'g, Owner, U> RefsGuard<'g, Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Referencing part
+    #[inline]
+    pub fn user(&self) -> &<U as DerefWithLifetime<'g>>::Target {
+        unsafe { self.srs.user.deref_with_lifetime() }
+    }
+
+    /// Owner
+    #[inline]
+    pub fn owner(&self) -> &'g Owner {
+        self.srs.owner.deref()
+    }
+}
+
+impl<'g, Owner, U> Clone for RefsGuard<'g, Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'g, Owner, U> Copy for RefsGuard<'g, Owner, U> where U: for<'b> DerefWithLifetime<'b> {}
+
+impl<Owner, U> SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Runs `f` with a guard that can be used for repeated access to user and owner,
+    /// e.g. in a read heavy loop, see `RefsGuard`.
+    ///
+    /// Guard is passed to the closure instead of being returned, because with a returned guard
+    /// `'g` would be an ordinary inferred lifetime that can shrink to the borrows of the guard itself,
+    /// so `Cell` in referencing part could store a reference to the referencing part,
+    /// which would dangle once `SRS` is moved (see `tests/ui/fail/refs_cell_self_ref.rs`).
+    /// Here `'g` is chosen by `refs`, so it outlives any borrow of the guard inside the closure.
+    pub fn refs<F, R>(&self, f: F) -> R
+    where
+        F: for<'g> FnOnce(RefsGuard<'g, Owner, U>) -> R,
+    {
+        f(RefsGuard {
+            srs: self,
+            _invariant: PhantomData,
+        })
+    }
+}
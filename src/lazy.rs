@@ -0,0 +1,89 @@
+use crate::{AliasedBox, DerefWithLifetime};
+use alloc::boxed::Box;
+use core::cell::OnceCell;
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+
+/// `SRS` that builds its referencing part only on first access.
+///
+/// Useful when building of references is expensive and they might be not needed at all.
+/// Builder is a plain `fn` so it is guaranteed to not capture anything.
+pub struct LazySRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    user: ManuallyDrop<OnceCell<U>>,
+    owner: AliasedBox<Owner>,
+    build: for<'b> fn(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+}
+
+impl<Owner, U> LazySRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates `LazySRS`, `build` will be called on first `get_ref` or `with`
+    pub fn new(owner: Owner, build: for<'b> fn(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target) -> Self {
+        Self {
+            user: ManuallyDrop::new(OnceCell::new()),
+            owner: Box::new(owner).into(),
+            build,
+        }
+    }
+
+    /// Whether referencing part has been built already
+    pub fn is_built(&self) -> bool {
+        self.user.get().is_some()
+    }
+
+    fn force(&self) -> &U {
+        self.user.get_or_init(|| {
+            let user = (self.build)(self.owner.deref());
+            unsafe { <U as DerefWithLifetime>::move_with_lifetime_back(user) }
+        })
+    }
+
+    /// Same as `SRS::with`, builds referencing part first if necessary
+    #[inline]
+    pub fn with<'b, F, Z: 'static>(&'b mut self, f: F) -> Z
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Z,
+    {
+        self.force();
+        let owner = self.owner.deref();
+        let user = unsafe { self.user.get_mut().unwrap().deref_with_lifetime_mut() };
+        f(user, owner)
+    }
+
+    /// Same as `SRS::get_ref`, builds referencing part first if necessary
+    #[inline]
+    pub fn get_ref<'b, F, Z: ?Sized + 'static>(&'b self, f: F) -> &'b Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b Z,
+    {
+        let user = unsafe { self.force().deref_with_lifetime() };
+        f(user, self.owner.deref())
+    }
+}
+
+impl<Owner, U> Deref for LazySRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    type Target = Owner;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.owner.deref()
+    }
+}
+
+// references have to be destroyed before the data they point into,
+// so `user` is dropped explicitly and owner is dropped afterwards as a field
+impl<Owner, U> Drop for LazySRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.user) }
+    }
+}
@@ -8,8 +8,8 @@
 //! go without it you should do it. But sometimes you actually need to have a self referential struct.
 //! So here are some examples when you actually need `SRS`:
 //!  - If you have structure that is built on references
-//! (graph with Arena, or any structure built with slices on top of the string)
-//! and you want to be able move it to another thread, or put it into Vec.
+//!    (graph with Arena, or any structure built with slices on top of the string)
+//!    and you want to be able move it to another thread, or put it into Vec.
 //!  - If your api would be much better if you will be able to return self contained values.
 //!
 //! Does not support dependent lifetimes (yet?, is it actully needed/possible?)
@@ -96,16 +96,50 @@
 //! println!("{}",r.0.field);
 //! ```
 #![warn(missing_docs)]
-// use std::intrinsics::transmute;
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 // pub unsafe trait ExtendedWhileBorrowed:Movable {}
 
-use std::ops::Deref;
-use std::mem;
-use std::intrinsics::transmute;
-use std::ptr::NonNull;
-use std::fmt::{Debug, Formatter};
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Deref, Index, Range, RangeBounds};
+use core::mem::{self, ManuallyDrop};
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(not(feature = "checked"))]
+use core::mem::transmute;
+use core::ptr::NonNull;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::future::Future;
+use core::fmt::{Debug, Formatter};
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::cmp::Ordering;
+#[cfg(feature = "futures")]
+use futures::{Stream, StreamExt};
+
+#[cfg(feature = "arena")]
+pub mod arena;
+mod arc;
+mod guard;
+mod lazy;
+mod shared;
+mod thin;
+pub use arc::ArcSRS;
+pub use guard::RefsGuard;
+pub use lazy::LazySRS;
+pub use shared::{analyze, SharedOwner, SharedSRS};
+pub use thin::SRSThin;
+#[cfg(feature = "derive")]
+pub use gsrs_derive::DerefWithLifetime;
 // use std::marker::PhantomPinned;
-// use std::pin::Pin;
+// use core::pin::Pin;
 
 // pub unsafe trait Movable:Unpin{}
 // unsafe impl<T:Unpin> Movable for Box<T>{}
@@ -118,20 +152,21 @@ use std::fmt::{Debug, Formatter};
 /// and in the end it will be dropped automatically or you can use `split` to keep some parts if necessary.f
 ///
 /// If you want to add additional owned values you will need arena-like structure like Arena from `typed_arena`
+/// or, if their number is fixed, just a tuple as `Owner`, all its fields are boxed together.
 ///
 /// If `Owner` type can be extended while there are references to existing data, like Arena,
 /// you can use `default` otherwise `new` is the only way to create it
 ///
 /// It is recommended to annotate lifetime used for `DerefWithLifetime` impl as `'static` when creating `SRS`
 /// otherwise it might be impossible to move it.
-#[derive(Debug)]
-pub struct SRS<Owner, U>
+pub struct SRS<Owner: ?Sized, U>
 where
     U: for<'b> DerefWithLifetime<'b>,
 {
-    // user have to be before owner for proper Drop call order
-    // user: AliasedBox<U>,
-    user: U,
+    // user is dropped explicitly in `Drop` before owner, so field order doesn't matter
+    // `SRS` is covariant over `U` same as `U` itself, it is sound
+    // because `U` lifetime is never used directly, it is always replaced with `DerefWithLifetime`
+    user: ManuallyDrop<U>,
     // Box is required to prevent user to get reference to owner field, because it would be invalid after move
     // so it would be possible to move SRS safely
     // Technically i think it can also be done by providing some king of collection trait but
@@ -142,12 +177,23 @@ where
     owner: AliasedBox<Owner>,
 }
 
-// uncomment if U is UnsafeCell
-// unsafe impl<Owner,U> Sync for SRS<Owner,U>
-//     where
-//         U: for<'b> DerefWithLifetime<'b>+Sync,
-//         Owner: Sync
-// {}
+/// Prints only owner, referencing part is shown as `..`, so it doesn't need to implement `Debug`
+impl<Owner: Debug + ?Sized, U> Debug for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SRS").field("owner", &self.owner).finish_non_exhaustive()
+    }
+}
+
+// `NonNull` in `AliasedBox` disables auto impl, but owner is uniquely owned same as with `Box`
+// and all references in `U` point inside of it, so they travel to another thread together
+unsafe impl<Owner: Send + ?Sized, U> Send for SRS<Owner, U> where U: for<'b> DerefWithLifetime<'b> + Send {}
+
+// shared `SRS` only gives shared access to both parts, it is gated by `U: Sync`,
+// so referencing parts with interior mutability that aliases owner are not `Sync`
+unsafe impl<Owner: Sync + ?Sized, U> Sync for SRS<Owner, U> where U: for<'b> DerefWithLifetime<'b> + Sync {}
 
 impl<Owner: Default, U: Default> Default for SRS<Owner, U>
 where
@@ -161,7 +207,7 @@ where
     }
 }
 
-impl<'a, Owner: 'a, U: Default> SRS<Owner, U>
+impl<Owner, U: Default> SRS<Owner, U>
 where
     U: for<'b> DerefWithLifetime<'b>,
 {
@@ -173,6 +219,19 @@ where
             user: Default::default(),
         }
     }
+
+    /// Same as `new` but owner is allocated with `alloc`, e.g. bump allocator, instead of `Box`.
+    ///
+    /// Unless owner is zero-sized, `split` and its variants panic for such `SRS`, because owner can't be moved into `Box`,
+    /// methods returning owner by value move it out of the allocator.
+    /// Allocator type is erased, so it has to be `Send` and `Sync` to not break auto traits of `SRS`.
+    #[cfg(feature = "allocator_api")]
+    pub fn new_in<A: core::alloc::Allocator + Send + Sync + 'static>(owner: Owner, alloc: A) -> Self {
+        Self {
+            owner: AliasedBox::new_in(owner, alloc),
+            user: Default::default(),
+        }
+    }
 }
 
 // pub trait TypeEquals {
@@ -188,7 +247,7 @@ where
 //     }
 // }
 
-impl<'a, Owner: 'a, U> SRS<Owner, U>
+impl<Owner: ?Sized, U> SRS<Owner, U>
 where
     U: for<'b> DerefWithLifetime<'b>,
 {
@@ -212,25 +271,117 @@ where
     #[inline]
     pub fn create_with<'b, F>(owner: Owner, f: F) -> Self
     where
+        Owner: Sized,
         F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
         Owner: 'b,
         U: 'b,
     {
+        // `AliasedBox` owns the allocation, so if `f` panics owner is dropped while unwinding
         let owner: AliasedBox<Owner> = Box::new(owner).into();
 
         let owner_ref = owner.deref();
         let user = unsafe {
             // transmute here also just changes lifetime
-            <U as DerefWithLifetime>::move_with_lifetime_back(f(transmute(owner_ref)))
+            #[cfg(not(feature = "checked"))]
+            let owner_ref = transmute::<&Owner, &'b Owner>(owner_ref);
+            #[cfg(feature = "checked")]
+            let owner_ref = &*(owner_ref as *const Owner);
+            <U as DerefWithLifetime>::move_with_lifetime_back(f(owner_ref))
+        };
+
+        Self {
+            owner,
+            user: ManuallyDrop::new(user),
+        }
+    }
+
+    /// Same as `create_with` but also moves `ctx` into `f`, so builder can use non-`'static` data
+    /// like borrowed configuration. `f` has to work for any lifetime of owner, so nothing borrowed
+    /// from `ctx` can end up in the referencing part.
+    pub fn create_with_ctx<C, F>(owner: Owner, ctx: C, f: F) -> Self
+    where
+        Owner: Sized,
+        F: for<'b> FnOnce(&'b Owner, C) -> <U as DerefWithLifetime<'b>>::Target,
+    {
+        let owner: AliasedBox<Owner> = Box::new(owner).into();
+        let user = unsafe {
+            let owner_ref = &*(owner.ptr.as_ptr() as *const Owner);
+            <U as DerefWithLifetime>::move_with_lifetime_back(f(owner_ref, ctx))
         };
 
-        Self { owner, user }
+        Self {
+            owner,
+            user: ManuallyDrop::new(user),
+        }
+    }
+
+    /// Same as `create_with` but takes already boxed owner, so it can be unsized, e.g. `dyn Trait`
+    pub fn from_boxed_dyn<'b, F>(owner: Box<Owner>, f: F) -> Self
+    where
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        Owner: 'b,
+        U: 'b,
+    {
+        let owner: AliasedBox<Owner> = owner.into();
+
+        let owner_ref = unsafe { &*(owner.deref() as *const Owner) };
+        let user = unsafe { <U as DerefWithLifetime>::move_with_lifetime_back(f(owner_ref)) };
+
+        Self {
+            owner,
+            user: ManuallyDrop::new(user),
+        }
+    }
+
+    /// Fallible version of `create_with`, on error owner is moved back out of the box and returned with it.
+    ///
+    /// Error is `'static` so it can't contain references to the owner that is returned
+    pub fn try_create_with<'b, F, E: 'static>(owner: Owner, f: F) -> Result<Self, (Owner, E)>
+    where
+        Owner: Sized,
+        F: 'static + FnOnce(&'b Owner) -> Result<<U as DerefWithLifetime<'b>>::Target, E>,
+        Owner: 'b,
+        U: 'b,
+    {
+        let owner: AliasedBox<Owner> = Box::new(owner).into();
+
+        let owner_ref = unsafe { &*(owner.deref() as *const Owner) };
+        match f(owner_ref) {
+            Ok(user) => Ok(Self {
+                user: ManuallyDrop::new(unsafe { <U as DerefWithLifetime>::move_with_lifetime_back(user) }),
+                owner,
+            }),
+            Err(err) => Err((*owner.into_box(), err)),
+        }
+    }
+
+    /// Async version of `create_with`, future returned by `f` can hold owner reference across awaits.
+    ///
+    /// Owner is boxed before `f` is called and the box is not dropped before the future,
+    /// so same lifetime reasoning as for `create_with` applies.
+    pub async fn create_with_async<'b, F, Fut>(owner: Owner, f: F) -> Self
+    where
+        Owner: Sized,
+        F: 'static + FnOnce(&'b Owner) -> Fut,
+        Fut: Future<Output = <U as DerefWithLifetime<'b>>::Target>,
+        Owner: 'b,
+        U: 'b,
+    {
+        let owner: AliasedBox<Owner> = Box::new(owner).into();
+
+        let owner_ref = unsafe { &*(owner.deref() as *const Owner) };
+        let user = f(owner_ref).await;
+
+        Self {
+            user: ManuallyDrop::new(unsafe { <U as DerefWithLifetime>::move_with_lifetime_back(user) }),
+            owner,
+        }
     }
 
     /// Splits `SRS` into owned and borrowed parts.
     ///
     /// Be careful because reverse operation is impossible because there is no way to know that references,
-    /// that we will bundle with `Owner`, are actually all pointing inside `Owner`.
+    /// that we will bundle with `Owner`, are actually all pointing inside `Owner`, only unsafe `from_parts` can do it.
     ///
     /// It requires some existing `Owner` because it needs place where to move it out and get lifetime from.
     /// ```
@@ -246,11 +397,86 @@ where
     /// let r = srs.split(&mut ow);
     /// println!("{}",r.0.unwrap().field);
     /// ```
+    ///
+    /// # Panics
+    /// If owner was allocated with `new_in`, because it can't be moved into `Box`.
     #[inline]
-    pub fn split<'b>(mut self, new: &'b mut Box<Owner>) -> <U as DerefWithLifetime<'b>>::Target {
-        let owner = unsafe { &mut *(&mut self.owner as *mut _ as *mut Box<Owner>) };
-        mem::swap(new, owner);
-        unsafe { self.user.move_with_lifetime() }
+    pub fn split<'b>(self, new: &'b mut Box<Owner>) -> <U as DerefWithLifetime<'b>>::Target {
+        // checked before parts are taken apart, so on panic they are still dropped in the right order
+        self.owner.assert_global();
+        let (user, mut owner) = self.into_parts();
+        // only raw pointers are swapped, any access to owner as `Box` would've invalidated references,
+        // spare owner that came from `new` is dropped with `owner`
+        let new = unsafe { &mut *(new as *mut Box<Owner>).cast::<NonNull<Owner>>() };
+        mem::swap(new, &mut owner.ptr);
+        unsafe { user.move_with_lifetime() }
+    }
+
+    // moves parts out without running `Drop`, caller is responsible to drop `U` before owner
+    fn into_parts(self) -> (U, AliasedBox<Owner>) {
+        let this = ManuallyDrop::new(self);
+        unsafe { (core::ptr::read(&*this.user), core::ptr::read(&this.owner)) }
+    }
+
+    /// Same as `split` but moves original boxed owner into empty `slot`, so no spare owner is allocated.
+    ///
+    /// Box can't be returned by value together with references because nothing would prevent
+    /// dropping it while references are alive, so it is borrowed from `slot` instead.
+    /// ```
+    /// use gsrs::SRS;
+    /// let srs = SRS::<_, Vec<&'static str>>::create_with("a b".to_owned(), |s| s.split(' ').collect());
+    /// let mut slot = None;
+    /// let (owner, refs) = srs.split_into_box(&mut slot);
+    /// assert_eq!(("a b", vec!["a", "b"]), (owner.as_str(), refs));
+    /// ```
+    ///
+    /// # Panics
+    /// If owner was allocated with `new_in`, same as `split`.
+    pub fn split_into_box<'b>(
+        self,
+        slot: &'b mut Option<Box<Owner>>,
+    ) -> (&'b Owner, <U as DerefWithLifetime<'b>>::Target) {
+        self.owner.assert_global();
+        let (user, owner) = self.into_parts();
+        *slot = None;
+        // same as in `split`, only raw pointer is moved into `slot` to keep references valid,
+        // `Option<Box<_>>` has the same layout as `Box<_>`
+        let ptr = owner.ptr;
+        mem::forget(owner);
+        unsafe {
+            core::ptr::write((slot as *mut Option<Box<Owner>>).cast::<NonNull<Owner>>(), ptr);
+            (&*ptr.as_ptr(), user.move_with_lifetime())
+        }
+    }
+
+    /// Reverse of `split`, bundles boxed owner with referencing part again.
+    ///
+    /// # Safety
+    /// **Every** reference in `user` must point inside `owner` (into its own memory or into heap data
+    /// it owns and keeps alive), otherwise they can dangle after `owner` is moved or dropped.
+    /// Also `U` must not hold any other non-`'static` data, since `'b` is replaced with
+    /// the lifetime of `SRS` borrow.
+    pub unsafe fn from_parts<'b>(owner: Box<Owner>, user: <U as DerefWithLifetime<'b>>::Target) -> Self {
+        Self {
+            owner: owner.into(),
+            user: ManuallyDrop::new(<U as DerefWithLifetime>::move_with_lifetime_back(user)),
+        }
+    }
+
+    /// Same as `split` but also reports length and capacity of the owner that was moved into `new`,
+    /// for example to decide whether it is worth reusing
+    ///
+    /// # Panics
+    /// If owner was allocated with `new_in`, same as `split`.
+    pub fn split_with_info<'b>(self, new: &'b mut Box<Owner>) -> (<U as DerefWithLifetime<'b>>::Target, OwnerInfo)
+    where
+        Owner: HasCapacity,
+    {
+        let info = OwnerInfo {
+            len: self.owner.len(),
+            capacity: self.owner.capacity(),
+        };
+        (self.split(new), info)
     }
 
     /// ### Main interface to modify `SRS`
@@ -265,13 +491,23 @@ where
     pub fn with<'b, F, Z: 'static>(&'b mut self, f: F) -> Z
     where
         for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Z,
-        'a: 'b,
     {
         let owner = self.owner.deref();
         let user = unsafe { self.user.deref_with_lifetime_mut() };
         f(user, owner)
     }
 
+    /// Same as `with` for fallible closures.
+    ///
+    /// On `Err` referencing part keeps changes made before the error, it is still valid
+    #[inline]
+    pub fn try_with<'b, F, Z: 'static, E: 'static>(&'b mut self, f: F) -> Result<Z, E>
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Result<Z, E>,
+    {
+        self.with(f)
+    }
+
     /// ### Method for using 'SRS'
     /// Allows you to get existing self reference to use it outside
     ///
@@ -281,188 +517,1907 @@ where
     pub fn get_ref<'b, F, Z: ?Sized + 'static>(&'b self, f: F) -> &'b Z
     where
         for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b Z,
-        'a: 'b,
     {
         let owner = self.owner.deref();
         let user = unsafe { self.user.deref_with_lifetime() };
         f(user, owner)
     }
 
-    // pub fn get<'b, F, Z: 'static>(&'b self, f: F) -> Z
-    //     where
-    //         for <'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> Z,
-    //         'a: 'b,
-    // {
-    //     let user = unsafe { self.user.deref_with_lifetime() };
-    //     f(user)
-    // }
-}
-
-impl<'a, Owner: 'a, U> Deref for SRS<Owner, U>
-where
-    U: for<'b> DerefWithLifetime<'b>,
-{
-    type Target = Owner;
+    /// Pins `SRS` on the heap, e.g. to store it in a pinned future.
+    ///
+    /// Owner is already boxed, so nothing additional is pinned, use `with_pinned` and `get_ref_pinned` to access it
+    pub fn pinned(self) -> Pin<Box<Self>> {
+        Box::pin(self)
+    }
 
+    /// Same as `with` for pinned `SRS`
     #[inline]
-    fn deref(&self) -> &Self::Target {
-        self.owner.deref()
+    pub fn with_pinned<'b, F, Z: 'static>(self: Pin<&'b mut Self>, f: F) -> Z
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Z,
+    {
+        // `with` never moves neither `SRS` nor its parts
+        unsafe { self.get_unchecked_mut() }.with(f)
     }
-}
 
-// technically default drop is safe for current rust version
-// but manually implementing drop is more future proof
-// in case rust will allow to run particular code only if lifetime is static
-// because in that case malicious drop impls will be able to save inner references in outer static variables
-// impl<'a, Owner: 'a, U> Drop for SRS<Owner, U>
-//     where
-//         U: for<'b> DerefWithLifetime<'b>,
-// {
-//     fn drop<'a>(&'a mut self) {
-//         unsafe {
-//             drop_in_place(<U as DerefWithLifetime<'a>>::deref_with_lifetime_mut(&mut self.user));
-//             drop_in_place(&mut self.owner)
-//         }
-//     }
-// }
+    /// Same as `get_ref` for pinned `SRS`
+    #[inline]
+    pub fn get_ref_pinned<'b, F, Z: ?Sized + 'static>(self: Pin<&'b Self>, f: F) -> &'b Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b Z,
+    {
+        Pin::get_ref(self).get_ref(f)
+    }
 
-struct AliasedBox<U: ?Sized> {
-    ptr: NonNull<U>,
-}
+    /// Same as `get_ref` but gives mutable access to the owner and returns mutable reference into it.
+    ///
+    /// It has to be `unsafe` even with `'static` bound on `f` as in `with`: that bound only prevents
+    /// `f` from smuggling references in or out, but `&mut Owner` still allows e.g. `String::clear`
+    /// on the very data referencing part points to, which types can't rule out.
+    ///
+    /// # Safety
+    /// Mutation through `&mut Owner` or the returned reference must not touch any data referenced by
+    /// referencing part, otherwise its references would dangle or alias mutable data.
+    /// Referencing part must not point into memory of `Owner` itself, only into heap data it owns,
+    /// for example `(String, Vec<u32>)` owner with references only into the `String`.
+    #[inline]
+    pub unsafe fn get_ref_mut<'b, F, Z: ?Sized + 'static>(&'b mut self, f: F) -> &'b mut Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b mut Owner) -> &'b mut Z,
+    {
+        let owner = &mut *self.owner.ptr.as_ptr();
+        f(self.user.deref_with_lifetime(), owner)
+    }
 
-impl<U: Default + ?Sized> Default for AliasedBox<U> {
-    fn default() -> Self {
-        Box::new(U::default()).into()
+    /// Same as `get_ref` but clones projected value,
+    /// so the result doesn't borrow `SRS` and can be used after it is dropped
+    #[inline]
+    pub fn get_cloned<'b, F, T: Clone + 'static>(&'b self, f: F) -> T
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b T,
+    {
+        self.get_ref(f).clone()
     }
-}
 
-impl<U: Debug> Debug for AliasedBox<U> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        self.deref().fmt(f)
+    /// Edits `changed` range of the owner with `edit` and rebuilds only references affected by it.
+    ///
+    /// References returned by `project_mut` that overlap `changed` are removed before `edit` is called,
+    /// then references created by `rebuild_affected` for `changed` range are inserted in their place.
+    /// References are expected to be ordered by their position in the owner.
+    ///
+    /// # Safety
+    /// `edit` must only write bytes of the owner inside of `changed`, without moving or reallocating
+    /// owner data, and must access them only through raw pointers or references covering just that range
+    /// (e.g. `as_mut_vec().as_mut_ptr().add(changed.start)` for `String`), otherwise references
+    /// outside of `changed` become invalid. Owner must stay valid, e.g. UTF-8 for `String`.
+    /// Referencing part must not hold other references into `changed` or references to owner itself.
+    pub unsafe fn rebuild_range<'b, P, E, F>(&'b mut self, changed: Range<usize>, project_mut: P, edit: E, rebuild_affected: F)
+    where
+        Owner: AsRef<[u8]>,
+        for<'x> P: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target) -> &'x mut Vec<&'b str>,
+        E: FnOnce(&mut Owner),
+        F: 'static + FnOnce(&'b Owner, Range<usize>) -> Vec<&'b str>,
+    {
+        let base = (*self.owner.ptr.as_ptr()).as_ref().as_ptr() as usize;
+        let (start, end) = (base + changed.start, base + changed.end);
+        let overlaps = |it: &&str| it.as_ptr() as usize + it.len() > start && (it.as_ptr() as usize) < end;
+        let refs = project_mut(self.user.deref_with_lifetime_mut());
+        // stale references must be gone before the edit, even moving them afterwards is invalid
+        let at = refs.iter().position(|it| it.as_ptr() as usize + it.len() > start).unwrap_or(refs.len());
+        refs.retain(|it| !overlaps(it));
+
+        edit(&mut *self.owner.ptr.as_ptr());
+        let owner = &*(self.owner.ptr.as_ptr() as *const Owner);
+        refs.splice(at..at, rebuild_affected(owner, changed));
     }
-}
 
-impl<U: ?Sized> Deref for AliasedBox<U> {
-    type Target = U;
+    #[cfg(feature = "std")]
+    /// Looks up `key` in the map returned by `project` from referencing part.
+    ///
+    /// Found slice is returned with the lifetime of `SRS` borrow attached
+    pub fn get_by_key<'b, F>(&'b self, key: &str, project: F) -> Option<&'b str>
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> &'x HashMap<String, &'b str>,
+    {
+        let user = unsafe { self.user.deref_with_lifetime() };
+        project(user).get(key).copied()
+    }
 
+    /// Read only version of `with`.
+    ///
+    /// Runs `f` over both parts and returns owned `'static` value computed from them
     #[inline]
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*(self.ptr.as_ref() as *const _ as *const Self::Target) }
-        // unsafe { self.ptr.as_ref() }
+    pub fn query<'b, F, Z: 'static>(&'b self, f: F) -> Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Z,
+    {
+        let owner = self.owner.deref();
+        let user = unsafe { self.user.deref_with_lifetime() };
+        f(user, owner)
     }
-}
 
-// impl<U: ?Sized> AliasedBox<U>{
-//     fn into(self) -> Box<U> {
-//         unsafe {
-//             let ptr = self.ptr.as_ptr();
-//             mem::forget(self);
-//             Box::from_raw(ptr)
-//         }
-//     }
-// }
+    /// Consumes `SRS` and returns iterator over owned clones of referenced items.
+    ///
+    /// Since references can't outlive `SRS`, all items are cloned upfront.
+    pub fn into_cloned_iter<F, T: Clone + 'static>(self, project: F) -> impl Iterator<Item = T>
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'x>>::Target) -> &'x [&'x T],
+    {
+        let user = unsafe { self.user.deref_with_lifetime() };
+        let items = project(user).iter().map(|&it| it.clone()).collect::<Vec<_>>();
+        items.into_iter()
+    }
 
-impl<U: ?Sized> From<Box<U>> for AliasedBox<U> {
-    #[inline]
-    fn from(from: Box<U>) -> Self {
-        unsafe {
-            AliasedBox {
-                ptr: NonNull::new_unchecked(Box::into_raw(from) as *mut _),
-            }
-        }
+    /// Returns number of references in referencing part as reported by `project`,
+    /// e.g. `srs.ref_count(|user| user.0.len())`.
+    ///
+    /// For referencing parts implementing `UserLen` there is `user_len`
+    pub fn ref_count<'b, F>(&'b self, project: F) -> usize
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> usize,
+    {
+        project(unsafe { self.user.deref_with_lifetime() })
     }
-}
 
-impl<U: ?Sized> Drop for AliasedBox<U> {
-    fn drop(&mut self) {
-        unsafe { Box::from_raw(self.ptr.as_ptr()) };
-    }
-}
-
-// /// This one is the most efficient but most restrictive.
-// ///
-// ///
-// #[derive(Debug)]
-// pub struct SRSThin<U1, U2>
-// where
-//     U1: for<'b> DerefWithLifetime<'b>,
-//     U2: for<'b> DerefWithLifetime<'b>,
-// {
-//     user1: U1,
-//     user2: U2,
-//     pinned: PhantomPinned,
-// }
-//
-// impl<U1, U2> SRSThin<U1, U2>
-// where
-//     U1: for<'b> DerefWithLifetime<'b>,
-//     U2: for<'b> DerefWithLifetime<'b>,
-// {
-//
-//     pub fn new(part1: U1, part2: U2) -> Self {
-//         Self{
-//             user1: part1,
-//             user2: part2,
-//             pinned: PhantomPinned
-//         }
-//     }
-// }
+    /// Returns number of references in referencing part
+    pub fn user_len(&self) -> usize
+    where
+        for<'b> <U as DerefWithLifetime<'b>>::Target: UserLen,
+    {
+        unsafe { self.user.deref_with_lifetime() }.user_len()
+    }
 
-/// This trait should be implemented for any struct that will contain references to data inside `SRS`
-/// and it should be implemented for any lifetime.
-/// Basically it just allows to apply custom lifetime to struct
-///
-/// It is already implemented for pure references.
-/// In general `deref_with_lifetime' macro should be used to implement this trait safely.
-///
-/// It is unsafe because SRS expects implementations of this trait to only change lifetime.
-///
-/// TODO this will only be implemented with macro in future
-pub unsafe trait DerefWithLifetime<'a> {
-    /// Implementors should make `Target` a Self but generic over `'a`, see macro definition
-    type Target: 'a;
-    // type Static: 'static;
-    /// implementation should be just `transmute(self)` to only change lifetime
-    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target;
+    /// Iterates over references of referencing part, they borrow `SRS`
+    pub fn iter<'b, T: ?Sized + 'static>(&'b self) -> impl Iterator<Item = &'b T>
+    where
+        <U as DerefWithLifetime<'b>>::Target: UserIter<'b, Item = T>,
+    {
+        unsafe { self.user.deref_with_lifetime() }.user_refs().iter().copied()
+    }
 
-    /// implementation should be just `transmute(self)` to only change lifetime
-    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target;
+    /// Creates new `SRS` from owners of `self` and `other` combined by `join`
+    /// and referencing part built over combined owner by `rebuild`.
+    ///
+    /// Old references can't be reused because combined owner is at the different address
+    pub fn concat<'b, J, F>(self, other: Self, join: J, rebuild: F) -> Self
+    where
+        Owner: Sized,
+        J: FnOnce(&Owner, &Owner) -> Owner,
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        Owner: 'b,
+        U: 'b,
+    {
+        let owner = join(&self, &other);
+        drop(self);
+        drop(other);
+        Self::create_with(owner, rebuild)
+    }
 
-    /// implementation should be just `transmute(self)` to only change lifetime
-    unsafe fn move_with_lifetime(self) -> Self::Target;
+    /// Debugging utility, returns raw addresses of references in the referencing part
+    /// as collected by `project`, e.g. `|user| user.0.iter().map(|it| it.as_ptr() as _).collect()`.
+    ///
+    /// Can be used to check that references actually point inside the owner.
+    pub fn ref_ptrs<'b, F>(&'b self, project: F) -> Vec<*const ()>
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> Vec<*const ()>,
+    {
+        project(unsafe { self.user.deref_with_lifetime() })
+    }
 
-    /// implementation should be just `transmute(self)` to only change lifetime
-    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self;
+    /// Exports references as `[offset, len]` pairs in bytes relative to the buffer returned by `base`,
+    /// so they can be passed over FFI together with that buffer.
+    ///
+    /// Panics if some reference is outside of the buffer or offsets do not fit into `u32`
+    pub fn export_ffi_table<'b, T, B, F>(&'b self, base: B, project: F) -> Vec<[u32; 2]>
+    where
+        T: ?Sized + 'static,
+        B: FnOnce(&Owner) -> (*const u8, usize),
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> &'x [&'b T],
+    {
+        let (ptr, len) = base(self.owner.deref());
+        let start = ptr as usize;
+        let refs = project(unsafe { self.user.deref_with_lifetime() });
+        refs.iter()
+            .map(|&it| {
+                let size = mem::size_of_val(it);
+                let offset = (it as *const T as *const u8 as usize)
+                    .checked_sub(start)
+                    .filter(|offset| offset + size <= len)
+                    .expect("reference is outside of the exported buffer");
+                let to_u32 = |x: usize| u32::try_from(x).expect("offset does not fit into u32");
+                [to_u32(offset), to_u32(size)]
+            })
+            .collect()
+    }
 
-    // unsafe fn move_as_static(self) -> Self::Static;
-}
+    /// Consumes `SRS` returning owner and owned data `build` computed from references,
+    /// references are dropped before owner is returned.
+    pub fn into_bundle<F, B: 'static>(self, build: F) -> (Owner, B)
+    where
+        Owner: Sized,
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'x>>::Target, &'x Owner) -> B,
+    {
+        let bundle = build(unsafe { self.user.deref_with_lifetime() }, self.owner.deref());
+        let (user, owner) = self.into_parts();
+        drop(user);
+        (*owner.into_box(), bundle)
+    }
 
-unsafe impl<'a, Z: ?Sized + 'static> DerefWithLifetime<'a> for &'_ Z {
-    type Target = &'a Z;
+    /// Consumes `SRS` returning owner, references are dropped first.
+    ///
+    /// Unlike `split` no spare `Box` is needed.
+    pub fn into_owner(self) -> Owner
+    where
+        Owner: Sized,
+    {
+        let (user, owner) = self.into_parts();
+        drop(user);
+        *owner.into_box()
+    }
 
-    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target {
-        core::mem::transmute(self)
+    /// Converts owner with `f` and builds new referencing part for it with `g`.
+    ///
+    /// Old references are dropped before `f` is called.
+    pub fn map_owner<'b, O2, U2, F, G>(self, f: F, g: G) -> SRS<O2, U2>
+    where
+        Owner: Sized,
+        U2: for<'x> DerefWithLifetime<'x> + 'b,
+        O2: 'b,
+        F: FnOnce(Owner) -> O2,
+        G: 'static + FnOnce(&'b O2) -> <U2 as DerefWithLifetime<'b>>::Target,
+    {
+        SRS::create_with(f(self.into_owner()), g)
     }
 
-    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target {
-        core::mem::transmute(self)
+    /// Same as `with` but also gives mutable access to the owner, so it can be extended
+    /// while references to its contents are alive, e.g. `FixedVec` or `Vec<String>`.
+    ///
+    /// # Safety
+    /// `f` must only append to the owner, without removing, modifying, replacing or moving present contents,
+    /// and referencing part must reference only contents guaranteed to stay in place by `AppendOnly`,
+    /// not owner itself.
+    pub unsafe fn with_owner_mut<'b, F, Z: 'static>(&'b mut self, f: F) -> Z
+    where
+        Owner: AppendOnly,
+        for<'x> F: 'static + FnOnce(&'b mut Owner, &'x mut <U as DerefWithLifetime<'b>>::Target) -> Z,
+    {
+        let owner = &mut *self.owner.ptr.as_ptr();
+        f(owner, self.user.deref_with_lifetime_mut())
     }
 
-    unsafe fn move_with_lifetime(self) -> Self::Target {
-        core::mem::transmute(self)
+    /// Clones owner into new `SRS`, building its referencing part with `rebuild`.
+    ///
+    /// References can't be just cloned because they would point into the original owner.
+    pub fn clone_with<'b, F>(&self, rebuild: F) -> Self
+    where
+        Owner: Clone + 'b,
+        U: 'b,
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+    {
+        Self::create_with(self.owner.deref().clone(), rebuild)
     }
 
-    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
-        core::mem::transmute(this)
+    /// Replaces referencing part with the one `f` creates from it, e.g. to filter references.
+    ///
+    /// Process is aborted if `f` panics, because old referencing part is already moved into it.
+    pub fn map_user<F>(&mut self, f: F)
+    where
+        F: 'static + for<'b> FnOnce(<U as DerefWithLifetime<'b>>::Target, &'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+    {
+        let guard = AbortOnUnwind;
+        unsafe {
+            let user = ManuallyDrop::take(&mut self.user).move_with_lifetime();
+            let user = f(user, &*(self.owner.ptr.as_ptr() as *const Owner));
+            self.user = ManuallyDrop::new(<U as DerefWithLifetime>::move_with_lifetime_back(user));
+        }
+        mem::forget(guard);
     }
-}
 
-/// Macro to implement `DerefWithLifetime`
-///
-/// Currently only works for simple cases with one lifetime and no generic,
-/// but in future this will be the only way to implement trait
-#[macro_export]
-macro_rules! deref_with_lifetime {
+    /// Replaces owner in place reusing its allocation, references are dropped first
+    /// and then new ones are created with `rebuild` as in `create_with`.
+    ///
+    /// Process is aborted if `rebuild` or drop of old owner panics,
+    /// because there would be no valid referencing part to drop afterwards.
+    pub fn replace_owner<'b, F>(&mut self, new: Owner, rebuild: F)
+    where
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        Owner: Sized + 'b,
+        U: 'b,
+    {
+        let guard = AbortOnUnwind;
+        unsafe {
+            ManuallyDrop::drop(&mut self.user);
+            *self.owner.ptr.as_ptr() = new;
+            let owner_ref = &*(self.owner.ptr.as_ptr() as *const Owner);
+            self.user = ManuallyDrop::new(<U as DerefWithLifetime>::move_with_lifetime_back(rebuild(owner_ref)));
+        }
+        mem::forget(guard);
+    }
+
+    /// Moves referencing part out, leaving `U::default()` in its place until `set_user` is called.
+    ///
+    /// Returned value borrows `self`, so owner stays untouched while it is alive.
+    pub fn take_user<'b>(&'b mut self) -> <U as DerefWithLifetime<'b>>::Target
+    where
+        U: Default,
+    {
+        unsafe { mem::take(&mut *self.user).move_with_lifetime() }
+    }
+
+    /// Replaces referencing part with the one `f` builds as in `create_with`, old one is dropped
+    /// after `f` returns, so on panic `SRS` still holds old referencing part.
+    pub fn set_user<'b, F>(&mut self, f: F)
+    where
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        Owner: 'b,
+        U: 'b,
+    {
+        unsafe {
+            let owner_ref = &*(self.owner.ptr.as_ptr() as *const Owner);
+            *self.user = <U as DerefWithLifetime>::move_with_lifetime_back(f(owner_ref));
+        }
+    }
+
+    /// Fallible version of `set_user`, on `Err` existing referencing part is kept as is.
+    pub fn try_set_user<'b, F, E: 'static>(&mut self, f: F) -> Result<(), E>
+    where
+        F: 'static + FnOnce(&'b Owner) -> Result<<U as DerefWithLifetime<'b>>::Target, E>,
+        Owner: 'b,
+        U: 'b,
+    {
+        unsafe {
+            let owner_ref = &*(self.owner.ptr.as_ptr() as *const Owner);
+            *self.user = <U as DerefWithLifetime>::move_with_lifetime_back(f(owner_ref)?);
+        }
+        Ok(())
+    }
+
+    /// Validates references returned by `project_mut` and moves them from `len` bytes at `old_base`
+    /// to bit-identical copy of them at `new_base`, offsetting each one by `new_base - old_base`.
+    ///
+    /// Panics before moving anything if some reference is not inside of `len` bytes at `old_base`.
+    ///
+    /// # Safety
+    /// `len` bytes at `new_base` must be initialized copy of the ones at `old_base` that is also owned by `Owner`,
+    /// and is not going to be mutated or moved while `SRS` is alive, otherwise references become dangling.
+    pub unsafe fn relocate<'b, T, F>(&'b mut self, old_base: *const u8, new_base: *const u8, len: usize, project_mut: F)
+    where
+        T: ?Sized + RawParts,
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target) -> &'x mut [&'b T],
+    {
+        let refs = project_mut(self.user.deref_with_lifetime_mut());
+        let old = old_base as usize;
+        for it in refs.iter() {
+            let start = *it as *const T as *const u8 as usize;
+            assert!(
+                start >= old && start + mem::size_of_val(*it) <= old + len,
+                "reference is outside of the relocated bytes"
+            );
+        }
+        for it in refs {
+            // pointer is derived from `new_base` so it keeps provenance of the new buffer
+            let ptr = new_base.add(*it as *const T as *const u8 as usize - old);
+            *it = T::from_raw_parts(ptr, it.raw_len());
+        }
+    }
+
+    /// Sorts references returned by `project_mut` by `key` in place, without rebuilding them from owner
+    pub fn sort_user_by<'b, T, K, P, F>(&'b mut self, project_mut: P, key: F)
+    where
+        T: ?Sized + 'static,
+        K: Ord,
+        F: FnMut(&&T) -> K,
+        for<'x> P: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target) -> &'x mut Vec<&'b T>,
+    {
+        project_mut(unsafe { self.user.deref_with_lifetime_mut() }).sort_by_key(key);
+    }
+
+    /// Same as `get_ref2` but with separate closure for each reference,
+    /// e.g. to reuse two existing projections and have both views alive together
+    pub fn get_two<'b, A, B, FA, FB>(&'b self, fa: FA, fb: FB) -> (&'b A, &'b B)
+    where
+        A: ?Sized + 'static,
+        B: ?Sized + 'static,
+        for<'x> FA: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b A,
+        for<'x> FB: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b B,
+    {
+        self.get_ref2(move |user, owner| (fa(user, owner), fb(user, owner)))
+    }
+
+    /// Same as `get_ref` but single closure returns two references, both borrowing `SRS`.
+    ///
+    /// Useful when both are found together, e.g. key and value of the same entry
+    /// ```
+    /// use gsrs::SRS;
+    /// let srs = SRS::<_, Vec<&'static str>>::create_with("k=v".to_owned(), |s| s.split('=').collect());
+    /// let (key, value) = srs.get_ref2(|user, _| (user[0], user[1]));
+    /// assert_eq!(("k", "v"), (key, value));
+    /// ```
+    pub fn get_ref2<'b, F, Z1, Z2>(&'b self, f: F) -> (&'b Z1, &'b Z2)
+    where
+        Z1: ?Sized + 'static,
+        Z2: ?Sized + 'static,
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> (&'b Z1, &'b Z2),
+    {
+        f(unsafe { self.user.deref_with_lifetime() }, self.owner.deref())
+    }
+
+    /// Heap memory owned by the owner, not counting `SRS` itself
+    pub fn owner_heap_size(&self) -> usize
+    where
+        Owner: HeapSize,
+    {
+        self.owner.heap_size()
+    }
+
+    /// Folds references returned by `project` into `init` with `f`, stopping at the first `Err`.
+    ///
+    /// Accumulator and error are `'static` so references can't leak through them
+    pub fn try_fold_refs<'b, T, B, E, P, F>(&'b self, init: B, project: P, mut f: F) -> Result<B, E>
+    where
+        T: ?Sized + 'static,
+        B: 'static,
+        E: 'static,
+        for<'x> P: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> &'x [&'b T],
+        F: FnMut(B, &T) -> Result<B, E>,
+    {
+        project(unsafe { self.user.deref_with_lifetime() })
+            .iter()
+            .try_fold(init, |acc, &it| f(acc, it))
+    }
+
+    /// Same as `create_with` but `build` also gets byte `range` of the owner that references should point into.
+    ///
+    /// Returns owner back if `range` is out of bounds of the owner
+    pub fn create_with_range<'b, F>(owner: Owner, range: Range<usize>, build: F) -> Result<Self, Owner>
+    where
+        Owner: Sized,
+        Owner: AsRef<[u8]> + 'b,
+        F: 'static + FnOnce(&'b Owner, Range<usize>) -> <U as DerefWithLifetime<'b>>::Target,
+        U: 'b,
+    {
+        if range.start > range.end || range.end > owner.as_ref().len() {
+            return Err(owner);
+        }
+        Ok(Self::create_with(owner, move |owner| build(owner, range)))
+    }
+
+    /// Runs `f` as in `with`, restoring previous referencing part if it returns `Err`
+    pub fn transact<'b, F, E: 'static>(&'b mut self, f: F) -> Result<(), E>
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Result<(), E>,
+        <U as DerefWithLifetime<'b>>::Target: Clone,
+    {
+        let owner = self.owner.deref();
+        let user = unsafe { self.user.deref_with_lifetime_mut() };
+        let snapshot = user.clone();
+        let result = f(user, owner);
+        if result.is_err() {
+            *user = snapshot;
+        }
+        result
+    }
+
+    /// In debug builds checks that every string reference returned by `project` points inside the owner
+    /// and starts and ends on its `char` boundaries, does nothing in release builds.
+    ///
+    /// Safe slicing can't split a `char`, but references created by unsafe code like `relocate` can.
+    pub fn debug_assert_char_boundaries<'b, F>(&'b self, project: F)
+    where
+        Owner: AsRef<str>,
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> &'x [&'b str],
+    {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let text = self.owner.as_ref();
+        let base = text.as_ptr() as usize;
+        for it in project(unsafe { self.user.deref_with_lifetime() }) {
+            let start = (it.as_ptr() as usize).wrapping_sub(base);
+            let end = start.wrapping_add(it.len());
+            assert!(
+                start <= text.len() && end <= text.len(),
+                "reference {:?} is outside of the owner",
+                it
+            );
+            // reference itself is not valid UTF-8 here, so only its position is printed
+            assert!(
+                text.is_char_boundary(start) && text.is_char_boundary(end),
+                "reference at {}..{} is not on char boundaries",
+                start,
+                end
+            );
+        }
+    }
+
+    /// Compares sets of reference positions, returned by `project` e.g. as `(offset, len)` pairs
+    /// relative to the owner, of this and `other` `SRS`. Order of positions doesn't matter.
+    pub fn refs_eq<'b, F>(&'b self, other: &'b Self, project: F) -> bool
+    where
+        for<'x> F: 'static + Fn(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Vec<(usize, usize)>,
+    {
+        let positions = |srs: &'b Self| {
+            let mut positions = project(unsafe { srs.user.deref_with_lifetime() }, srs.owner.deref());
+            positions.sort_unstable();
+            positions.dedup();
+            positions
+        };
+        positions(self) == positions(other)
+    }
+
+    /// Async version of `get_ref`, future returned by `f` can hold references across awaits.
+    ///
+    /// Same as in `get_ref` referencing part is only borrowed for the call of `f`, so the future
+    /// can keep the owner and `'b` references copied out of it, but not referencing part itself.
+    pub fn get_ref_async<'b, F, Fut, Z: 'static>(&'b self, f: F) -> impl Future<Output = Z> + 'b
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Fut,
+        Fut: Future<Output = Z> + 'b,
+    {
+        f(unsafe { self.user.deref_with_lifetime() }, self.owner.deref())
+    }
+
+    /// Shrinks capacity of references vector returned by `project_mut` to its length
+    pub fn shrink_user_to_fit<'b, T, F>(&'b mut self, project_mut: F)
+    where
+        T: ?Sized + 'static,
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target) -> &'x mut Vec<&'b T>,
+    {
+        project_mut(unsafe { self.user.deref_with_lifetime_mut() }).shrink_to_fit();
+    }
+
+    /// Bytes of the owner, e.g. to hash its content
+    pub fn owner_bytes(&self) -> &[u8]
+    where
+        Owner: AsOwnerBytes,
+    {
+        self.owner.owner_bytes()
+    }
+
+    /// Builds references with `build` as `create_with`, uses them with `use_` to get owned result
+    /// and returns it together with the owner, no placeholder box is needed as for `split`.
+    pub fn build_refs_once<'b, B, F, R: 'static>(owner: Owner, build: B, use_: F) -> (Owner, R)
+    where
+        Owner: Sized,
+        B: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'x>>::Target, &'x Owner) -> R,
+        Owner: 'b,
+        U: 'b,
+    {
+        Self::create_with(owner, build).into_bundle(use_)
+    }
+
+    /// Whether referencing part holds any references, `is_empty` should report if it is empty,
+    /// e.g. `srs.has_refs(|user| user.0.is_empty())`
+    pub fn has_refs<'b, F>(&'b self, is_empty: F) -> bool
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> bool,
+    {
+        !is_empty(unsafe { self.user.deref_with_lifetime() })
+    }
+
+    /// Applies `transform` to the owner and then builds references to the result as `create_with`.
+    ///
+    /// It is sound because there are no references to the owner yet when it is mutated
+    pub fn transform_then_build<'b, T, F>(mut owner: Owner, transform: T, build: F) -> Self
+    where
+        Owner: Sized,
+        T: FnOnce(&mut Owner),
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        Owner: 'b,
+        U: 'b,
+    {
+        transform(&mut owner);
+        Self::create_with(owner, build)
+    }
+
+    /// Iterates over references returned by `project` in reverse order
+    pub fn rev_refs<'b, T, F>(&'b self, project: F) -> impl Iterator<Item = &'b T> + 'b
+    where
+        T: ?Sized + 'static,
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> &'x [&'b T],
+    {
+        project(unsafe { self.user.deref_with_lifetime() }).iter().rev().copied()
+    }
+
+    /// Checks internal invariants, intended as an oracle for fuzz targets.
+    ///
+    /// Panics if owner pointer is not aligned for `Owner`,
+    /// or if some of `(pointer, len)` references returned by `project` are outside of the owner's range.
+    #[cfg(any(test, fuzzing, feature = "fuzzing"))]
+    pub fn check_invariants<'b, F>(&'b self, project: F)
+    where
+        Owner: OwnsRange + Sized,
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> Vec<(*const u8, usize)>,
+    {
+        assert_eq!(0, self.owner.ptr.as_ptr() as usize % mem::align_of::<Owner>(), "owner pointer is misaligned");
+        let range = self.owner.owned_range();
+        for (ptr, len) in project(unsafe { self.user.deref_with_lifetime() }) {
+            let start = ptr as usize;
+            assert!(
+                range.start <= start && start + len <= range.end,
+                "reference {:?}..{:?} is outside of the owner range {:?}",
+                start,
+                start + len,
+                range
+            );
+        }
+    }
+
+    /// Pointer to the owner, e.g. to pass it to FFI.
+    ///
+    /// It stays the same when `SRS` is moved, because owner is boxed,
+    /// but it is invalidated when `SRS` is dropped or consumed by `split`, `into_owner` and similar methods.
+    #[inline]
+    pub fn owner_ptr(&self) -> *const Owner {
+        self.owner.ptr.as_ptr()
+    }
+
+    /// Returns owned value computed by `f` from referencing part,
+    /// like `with` but without mutable access and owner or like `get_ref` but returning values.
+    ///
+    /// ### Safety
+    /// Same as for `with`
+    #[inline]
+    pub fn get<'b, F, Z: 'static>(&'b self, f: F) -> Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target) -> Z,
+    {
+        let user = unsafe { self.user.deref_with_lifetime() };
+        f(user)
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<U> SRS<Vec<u8>, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates `SRS` by collecting async `stream` of chunks into single `Vec<u8>` owner
+    /// and then building self referencing part with `build` as in `create_with`.
+    ///
+    /// Owner is boxed only once after whole stream is collected,
+    /// so references created by `build` will never observe reallocation.
+    pub async fn from_stream<'b, S, F>(stream: S, build: F) -> Self
+    where
+        S: Stream<Item = Vec<u8>>,
+        F: 'static + FnOnce(&'b Vec<u8>) -> <U as DerefWithLifetime<'b>>::Target,
+        U: 'b,
+    {
+        futures::pin_mut!(stream);
+        let mut owner = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            if owner.is_empty() {
+                owner = chunk;
+            } else {
+                owner.extend_from_slice(&chunk);
+            }
+        }
+        Self::create_with(owner, build)
+    }
+}
+
+impl<O, U> SRS<Pin<Box<O>>, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates `SRS` from already pinned owner, `f` gets reference to the pinned value itself.
+    ///
+    /// Pinned value is never moved by `SRS`, `split` and `into_pinned_owner` give it back still pinned.
+    pub fn from_pinned_box<'b, F>(owner: Pin<Box<O>>, f: F) -> Self
+    where
+        F: 'static + FnOnce(&'b O) -> <U as DerefWithLifetime<'b>>::Target,
+        O: 'b,
+        U: 'b,
+    {
+        Self::create_with(owner, move |owner: &'b Pin<Box<O>>| f(owner.as_ref().get_ref()))
+    }
+
+    /// Drops referencing part and returns pinned owner
+    pub fn into_pinned_owner(self) -> Pin<Box<O>> {
+        let (user, owner) = self.into_parts();
+        drop(user);
+        *owner.into_box()
+    }
+}
+
+/// Serializes only owner, referencing part is expected to be rebuilt, see `SRS::deserialize_with`
+#[cfg(feature = "serde")]
+impl<Owner: serde::Serialize + ?Sized, U> serde::Serialize for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.deref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Owner, U> SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Deserializes owner and creates referencing part with `f` as in `create_with`.
+    ///
+    /// Referencing part can't be deserialized generically, so there is no `Deserialize` impl.
+    pub fn deserialize_with<'de, 'b, D, F>(deserializer: D, f: F) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        F: 'static + FnOnce(&'b Owner) -> <U as DerefWithLifetime<'b>>::Target,
+        Owner: serde::Deserialize<'de> + 'b,
+        U: 'b,
+    {
+        Owner::deserialize(deserializer).map(|owner| Self::create_with(owner, f))
+    }
+}
+
+/// Conversions with `yoke` crate.
+///
+/// Concepts map directly: `Yoke` cart is `SRS` owner and yokeable is referencing part.
+/// Since those are different traits referencing part is always rebuilt from the owner.
+#[cfg(feature = "yoke")]
+impl<C, U> SRS<C, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates `SRS` owning the cart of `yoke`, referencing part is created by `rebuild` as in `create_with`
+    pub fn from_yoke<'b, Y, F>(yoke: yoke::Yoke<Y, C>, rebuild: F) -> Self
+    where
+        Y: for<'a> yoke::Yokeable<'a>,
+        F: 'static + FnOnce(&'b C) -> <U as DerefWithLifetime<'b>>::Target,
+        C: 'b,
+        U: 'b,
+    {
+        Self::create_with(yoke.into_backing_cart(), rebuild)
+    }
+
+    /// Converts into `Yoke` with boxed owner as a cart, yokeable is created by `rebuild`
+    pub fn into_yoke<Y, F>(self, rebuild: F) -> yoke::Yoke<Y, Box<C>>
+    where
+        Y: for<'a> yoke::Yokeable<'a>,
+        F: for<'de> FnOnce(&'de C) -> <Y as yoke::Yokeable<'de>>::Output,
+        C: 'static,
+    {
+        let (user, owner) = self.into_parts();
+        drop(user);
+        yoke::Yoke::attach_to_cart(owner.into_box(), rebuild)
+    }
+}
+
+/// Leaking `SRS` for process-lifetime data
+#[cfg(feature = "leak")]
+impl<Owner: 'static, U> SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// **Leaks** both owner and referencing part, so they are never dropped or deallocated,
+    /// and returns them at `'static`, e.g. to put them into global cache.
+    ///
+    /// Memory is never reclaimed, so it should be called only bounded number of times.
+    pub fn into_static(self) -> (&'static Owner, &'static <U as DerefWithLifetime<'static>>::Target) {
+        let (user, owner) = self.into_parts();
+        let owner: &'static Owner = Box::leak(owner.into_box());
+        let user = Box::leak(Box::new(unsafe { user.move_with_lifetime() }));
+        (owner, user)
+    }
+}
+
+/// Building referencing part in parallel
+#[cfg(feature = "rayon")]
+impl<Owner: AsRef<str> + Sync, U> SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Splits owner string into `chunks` byte ranges on char boundaries, builds references
+    /// for every range in parallel with `build_chunk` and passes results in order to `merge`.
+    ///
+    /// Ranges can split words, so `build_chunk` is responsible for handling tokens crossing range borders,
+    /// e.g. by taking only tokens that start inside the range.
+    pub fn rebuild_par<'b, B, M>(&'b mut self, chunks: usize, build_chunk: B, merge: M)
+    where
+        B: for<'x> Fn(&'x Owner, Range<usize>) -> Vec<&'x str> + Sync,
+        for<'x> M: 'static + FnOnce(Vec<Vec<&'b str>>, &'x mut <U as DerefWithLifetime<'b>>::Target),
+    {
+        use rayon::prelude::*;
+
+        let owner: &'b Owner = self.owner.deref();
+        let text = owner.as_ref();
+        let chunk_len = text.len() / chunks.max(1) + 1;
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let mut end = (start + chunk_len).min(text.len());
+            while !text.is_char_boundary(end) {
+                end += 1;
+            }
+            ranges.push(start..end);
+            start = end;
+        }
+        let parts = ranges.into_par_iter().map(|range| build_chunk(owner, range)).collect();
+        merge(parts, unsafe { self.user.deref_with_lifetime_mut() });
+    }
+}
+
+impl<Owner> SRS<Owner, Vec<&'static str>> {
+    /// Creates `SRS` with references to tokens of `owner` produced by `tokenizer`
+    pub fn from_tokenizer<T>(owner: Owner, tokenizer: T) -> Self
+    where
+        T: Tokenizer<Owner> + 'static,
+    {
+        Self::create_with(owner, move |owner| tokenizer.tokenize(owner))
+    }
+}
+
+impl<T: 'static, U> SRS<Vec<Box<T>>, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates `SRS` over individually boxed items, `build` gets references to boxed contents.
+    ///
+    /// Contents of the boxes stay at the same address even if outer `Vec` is reallocated,
+    /// so new items can be added with `push_boxed` while references to existing ones are alive.
+    /// References must point at the contents (`&**item`), not at the `Box` slots of the `Vec`.
+    pub fn from_boxed_vec<'b, F>(items: Vec<Box<T>>, build: F) -> Self
+    where
+        F: 'static + FnOnce(&[&'b T]) -> <U as DerefWithLifetime<'b>>::Target,
+        U: 'b,
+    {
+        Self::create_with(items, move |items: &'b Vec<Box<T>>| {
+            let refs = items.iter().map(|it| &**it).collect::<Vec<_>>();
+            build(&refs)
+        })
+    }
+
+    /// Adds new boxed item to the owner
+    ///
+    /// # Safety
+    /// Referencing part must not contain references to the `Vec` itself or its `Box` slots,
+    /// only to the boxed contents, which is always the case if it was created by `from_boxed_vec`
+    /// and only references to contents were added with `with`.
+    pub unsafe fn push_boxed(&mut self, item: Box<T>) {
+        (*self.owner.ptr.as_ptr()).push(item);
+    }
+}
+
+/// Append-only owner from `elsa`, new items can be pushed with `&Owner` inside of `with`
+#[cfg(feature = "elsa")]
+impl<T, U> SRS<elsa::FrozenVec<Box<T>>, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Collects `iter` into `FrozenVec` of boxed items and builds referencing part with `build`
+    pub fn from_iter_frozen<'b, I, F>(iter: I, build: F) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        F: 'static + FnOnce(&'b elsa::FrozenVec<Box<T>>) -> <U as DerefWithLifetime<'b>>::Target,
+        T: 'b,
+        U: 'b,
+    {
+        Self::create_with(iter.into_iter().map(Box::new).collect(), build)
+    }
+}
+
+/// Memory mapped owners, e.g. for indexes shared between processes.
+///
+/// Mapping address is different in every process so references are passed as offsets into the mapping,
+/// for example created with `export_ffi_table`.
+#[cfg(feature = "memmap2")]
+impl SRS<memmap2::Mmap, Vec<&'static str>> {
+    /// Creates references to `(offset, len)` ranges of the mapping in this process.
+    ///
+    /// Returns mapping back if some range is out of bounds or is not valid UTF-8
+    pub fn from_shared_mmap(map: memmap2::Mmap, offsets: &[(usize, usize)]) -> Result<Self, memmap2::Mmap> {
+        let valid = offsets.iter().all(|&(offset, len)| {
+            offset
+                .checked_add(len)
+                .and_then(|end| map.get(offset..end))
+                .is_some_and(|bytes| core::str::from_utf8(bytes).is_ok())
+        });
+        if !valid {
+            return Err(map);
+        }
+        let offsets = offsets.to_vec();
+        Ok(Self::create_with(map, move |map| {
+            offsets
+                .iter()
+                // ranges are validated above
+                .map(|&(offset, len)| unsafe { core::str::from_utf8_unchecked(&map[offset..offset + len]) })
+                .collect()
+        }))
+    }
+}
+
+impl<T: 'static> SRS<Vec<T>, &'static [T]> {
+    /// Creates `SRS` referencing the whole owner as a slice
+    pub fn whole(owner: Vec<T>) -> Self {
+        Self::create_with(owner, |owner| &owner[..])
+    }
+}
+
+impl SRS<String, &'static str> {
+    /// Creates `SRS` referencing the whole owner as a `str`, same as `whole` for `Vec`
+    pub fn whole_str(owner: String) -> Self {
+        Self::create_with(owner, |owner| &owner[..])
+    }
+}
+
+impl SRS<String, Vec<&'static str>> {
+    /// Creates `SRS` with references to every line of `owner`
+    ///
+    /// ```
+    /// use gsrs::*;
+    /// let srs = SRS::lines("first\nsecond".to_owned());
+    /// assert_eq!(Some("second"), srs.line(1));
+    /// ```
+    pub fn lines(owner: String) -> Self {
+        Self::create_with(owner, |owner| owner.lines().collect())
+    }
+
+    /// Returns `n`-th line, if there is one
+    pub fn line(&self, n: usize) -> Option<&str> {
+        let user = unsafe { self.user.deref_with_lifetime() };
+        user.get(n).copied()
+    }
+}
+
+impl<Owner, T: ?Sized + 'static> SRS<Owner, Vec<&'static T>> {
+    /// Returns referenced items that match `filter`.
+    ///
+    /// Main use case is querying tree-like structure after it was moved,
+    /// e.g. when owner is an arena with parsed syntax tree nodes
+    /// and referencing part is a list of nodes of interest like all identifiers.
+    pub fn collect_nodes<F>(&self, filter: F) -> Vec<&T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let nodes = unsafe { self.user.deref_with_lifetime() };
+        nodes.iter().copied().filter(|&it| filter(it)).collect()
+    }
+}
+
+impl<Owner, T: ?Sized + Ord + 'static, V: 'static> SRS<Owner, BTreeMap<&'static T, V>> {
+    /// Iterates over ordered entries with keys in `bounds`
+    pub fn range<'b, R>(&'b self, bounds: R) -> impl Iterator<Item = (&'b T, &'b V)>
+    where
+        R: RangeBounds<&'b T>,
+    {
+        let map = unsafe { self.user.deref_with_lifetime() };
+        map.range(bounds).map(|(&k, v)| (k, v))
+    }
+}
+
+impl<T: 'static> SRS<Vec<T>, Vec<&'static T>> {
+    /// Creates `SRS` with references to all elements of `owner` sorted by `key`,
+    /// so it can be used with `search` afterwards
+    pub fn indexed<K: Ord, F>(owner: Vec<T>, key: F) -> Self
+    where
+        F: 'static + Fn(&T) -> K,
+    {
+        Self::create_with(owner, move |owner| {
+            let mut index = owner.iter().collect::<Vec<_>>();
+            index.sort_by_key(|&it| key(it));
+            index
+        })
+    }
+
+    /// Binary searches index created with `indexed` for element with key `k`.
+    ///
+    /// `key` should be the same as the one used for `indexed`,
+    /// otherwise result is unspecified like for `binary_search_by_key`
+    pub fn search<K: Ord, F>(&self, k: &K, key: F) -> Option<&T>
+    where
+        F: Fn(&T) -> K,
+    {
+        let index = unsafe { self.user.deref_with_lifetime() };
+        index
+            .binary_search_by(|&it| key(it).cmp(k))
+            .ok()
+            .map(|pos| index[pos])
+    }
+}
+
+impl<Owner: ?Sized, U> Deref for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    type Target = Owner;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.owner.deref()
+    }
+}
+
+/// Compares only owners, referencing part is ignored
+impl<Owner: PartialEq + ?Sized, U> PartialEq for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+impl<Owner: Eq + ?Sized, U> Eq for SRS<Owner, U> where U: for<'b> DerefWithLifetime<'b> {}
+
+/// Orders only by owners, consistently with `PartialEq`
+impl<Owner: PartialOrd + ?Sized, U> PartialOrd for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.deref().partial_cmp(other.deref())
+    }
+}
+
+impl<Owner: Ord + ?Sized, U> Ord for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+/// Hashes only owner, consistently with `PartialEq`
+impl<Owner: Hash + ?Sized, U> Hash for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+
+impl<Owner: ?Sized, U> AsRef<Owner> for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn as_ref(&self) -> &Owner {
+        self.deref()
+    }
+}
+
+/// Consistent with `PartialEq` and `Hash` which also only use owner
+impl<Owner: ?Sized, U> Borrow<Owner> for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn borrow(&self) -> &Owner {
+        self.deref()
+    }
+}
+
+// Output is only borrowed for the lifetime of `&self`, which is exactly what `Index` requires
+impl<Owner: ?Sized, U, T: ?Sized + 'static> Index<usize> for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+    for<'b> <U as DerefWithLifetime<'b>>::Target: UserIndex<'b, Output = T>,
+{
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        unsafe { self.user.deref_with_lifetime() }.user_index(index)
+    }
+}
+
+// owner is boxed so its address is not changed when `SRS` is moved
+#[cfg(feature = "stable_deref")]
+unsafe impl<Owner: ?Sized, U> stable_deref_trait::StableDeref for SRS<Owner, U> where U: for<'b> DerefWithLifetime<'b> {}
+
+impl<'b, Owner: ?Sized, U, T: ?Sized + 'static> IntoIterator for &'b SRS<Owner, U>
+where
+    U: for<'x> DerefWithLifetime<'x>,
+    <U as DerefWithLifetime<'b>>::Target: UserIter<'b, Item = T>,
+{
+    type Item = &'b T;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'b, &'b T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        unsafe { self.user.deref_with_lifetime() }.user_refs().iter().copied()
+    }
+}
+
+/// Same as `SRS::with` for two `SRS` at once, e.g. when one is used to update the other.
+///
+/// Both lifetimes are generic for `f`, so references from one `SRS` can't be put into the other.
+pub fn with_disjoint<O1, U1, O2, U2, F, Z: 'static>(a: &mut SRS<O1, U1>, b: &mut SRS<O2, U2>, f: F) -> Z
+where
+    O1: ?Sized,
+    O2: ?Sized,
+    U1: for<'x> DerefWithLifetime<'x>,
+    U2: for<'x> DerefWithLifetime<'x>,
+    F: 'static
+        + for<'p, 'q> FnOnce(
+            &mut <U1 as DerefWithLifetime<'p>>::Target,
+            &'p O1,
+            &mut <U2 as DerefWithLifetime<'q>>::Target,
+            &'q O2,
+        ) -> Z,
+{
+    let (user1, owner1) = unsafe { (a.user.deref_with_lifetime_mut(), a.owner.deref()) };
+    let (user2, owner2) = unsafe { (b.user.deref_with_lifetime_mut(), b.owner.deref()) };
+    f(user1, owner1, user2, owner2)
+}
+
+// panic while unwinding aborts, used when there is no valid referencing part to drop during unwinding
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    fn drop(&mut self) {
+        panic!("panic in `SRS` method while referencing part is moved out");
+    }
+}
+
+// references have to be destroyed before the data they point into,
+// so `user` is dropped explicitly and owner is dropped afterwards as a field
+impl<Owner: ?Sized, U> Drop for SRS<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.user) }
+    }
+}
+
+struct AliasedBox<U: ?Sized> {
+    ptr: NonNull<U>,
+    // Same as for `Box`: covariance over `U` makes `SRS` covariant over `Owner`,
+    // which is fine because owner is only ever accessed through shared references,
+    // and ownership of `U` tells dropck that `U` is dropped with the box.
+    // `NonNull` alone already gives covariance, but it should not silently change
+    // if pointer type is changed here, it is checked by tests/ui.
+    _marker: PhantomData<U>,
+    // frees memory allocated by `new_in`, `None` for memory from `Box`,
+    // pointer is erased to keep covariance
+    #[cfg(feature = "allocator_api")]
+    free: Option<unsafe fn(NonNull<()>, bool)>,
+}
+
+// owner is the first field so pointer to block is also pointer to owner
+#[cfg(feature = "allocator_api")]
+#[repr(C)]
+struct InAllocator<U, A> {
+    value: U,
+    alloc: A,
+}
+
+#[cfg(feature = "allocator_api")]
+unsafe fn free_in<U, A: core::alloc::Allocator>(ptr: NonNull<()>, drop_value: bool) {
+    let block = ptr.cast::<InAllocator<U, A>>().as_ptr();
+    if drop_value {
+        core::ptr::drop_in_place(core::ptr::addr_of_mut!((*block).value));
+    }
+    let alloc = core::ptr::read(core::ptr::addr_of!((*block).alloc));
+    alloc.deallocate(ptr.cast(), core::alloc::Layout::new::<InAllocator<U, A>>());
+}
+
+#[cfg(feature = "allocator_api")]
+impl<U> AliasedBox<U> {
+    // allocator is moved into the same block so it is known when freeing,
+    // its type is erased there, so `SRS` auto traits rely on it being `Send + Sync`
+    fn new_in<A: core::alloc::Allocator + Send + Sync + 'static>(value: U, alloc: A) -> Self {
+        // zero-sized owner needs no memory, so allocator is not needed to free it either,
+        // and `Box` of it is a dangling pointer, same as when it is allocated with `new`
+        if mem::size_of::<U>() == 0 {
+            drop(alloc);
+            return Box::new(value).into();
+        }
+        let layout = core::alloc::Layout::new::<InAllocator<U, A>>();
+        let block = match alloc.allocate(layout) {
+            Ok(block) => block.cast::<InAllocator<U, A>>(),
+            Err(_) => alloc::alloc::handle_alloc_error(layout),
+        };
+        unsafe { block.as_ptr().write(InAllocator { value, alloc }) };
+        AliasedBox {
+            ptr: block.cast(),
+            _marker: PhantomData,
+            free: Some(free_in::<U, A>),
+        }
+    }
+}
+
+impl<U: ?Sized> AliasedBox<U> {
+    // owner from custom allocator can't be given away as `Box`
+    #[inline]
+    fn assert_global(&self) {
+        #[cfg(feature = "allocator_api")]
+        assert!(self.free.is_none(), "owner allocated with `new_in` can't be moved into `Box`");
+    }
+}
+
+impl<U: Default> Default for AliasedBox<U> {
+    fn default() -> Self {
+        Box::new(U::default()).into()
+    }
+}
+
+impl<U: Debug + ?Sized> Debug for AliasedBox<U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<U: ?Sized> Deref for AliasedBox<U> {
+    type Target = U;
+
+    // Reference is created from `NonNull` every time, so unlike with `Box`, moving `AliasedBox`
+    // doesn't assert unique access to the owner and references in `U`, created the same way, stay valid.
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<U> AliasedBox<U> {
+    fn into_box(self) -> Box<U> {
+        unsafe {
+            let ptr = self.ptr.as_ptr();
+            #[cfg(feature = "allocator_api")]
+            if let Some(free) = self.free {
+                mem::forget(self);
+                let value = Box::new(core::ptr::read(ptr));
+                free(NonNull::new_unchecked(ptr).cast(), false);
+                return value;
+            }
+            mem::forget(self);
+            Box::from_raw(ptr)
+        }
+    }
+}
+
+// `Box` of zero-sized owner is already a dangling pointer that is never allocated or freed
+impl<U: ?Sized> From<Box<U>> for AliasedBox<U> {
+    #[inline]
+    fn from(from: Box<U>) -> Self {
+        unsafe {
+            AliasedBox {
+                ptr: NonNull::new_unchecked(Box::into_raw(from) as *mut _),
+                _marker: PhantomData,
+                #[cfg(feature = "allocator_api")]
+                free: None,
+            }
+        }
+    }
+}
+
+impl<U: ?Sized> Drop for AliasedBox<U> {
+    fn drop(&mut self) {
+        #[cfg(feature = "allocator_api")]
+        if let Some(free) = self.free {
+            return unsafe { free(self.ptr.cast(), true) };
+        }
+        unsafe { drop(Box::from_raw(self.ptr.as_ptr())) };
+    }
+}
+
+/// Referencing parts that are collections of references and can report their length
+///
+/// See `SRS::user_len`
+pub trait UserLen {
+    /// Number of references in the referencing part
+    fn user_len(&self) -> usize;
+}
+
+impl<T> UserLen for Vec<T> {
+    fn user_len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> UserLen for HashMap<K, V, S> {
+    fn user_len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Referencing parts that can be indexed by position, allows to use `srs[i]`
+///
+/// It is implemented for `Vec<&T>`, and can be implemented for custom referencing structs,
+/// For example:
+/// ```
+/// use gsrs::*;
+/// struct Words<'a>(Vec<&'a str>);
+/// deref_with_lifetime!(Words);
+/// impl<'a> UserIndex<'a> for Words<'a> {
+///     type Output = str;
+///     fn user_index(&self, index: usize) -> &'a str {
+///         self.0[index]
+///     }
+/// }
+/// let srs = SRS::<_, Words>::create_with("a b".to_owned(), |owner| Words(owner.split(' ').collect()));
+/// assert_eq!("b", &srs[1]);
+/// ```
+pub trait UserIndex<'a> {
+    /// Referenced type
+    type Output: ?Sized + 'static;
+
+    /// Returns reference at `index`, should panic if it is out of bounds
+    fn user_index(&self, index: usize) -> &'a Self::Output;
+}
+
+impl<'a, T: ?Sized + 'static> UserIndex<'a> for Vec<&'a T> {
+    type Output = T;
+
+    fn user_index(&self, index: usize) -> &'a T {
+        self[index]
+    }
+}
+
+/// Referencing parts that are slices of references, allows to use `SRS::iter`
+///
+/// It is implemented for `Vec<&T>`, and can be implemented for custom referencing structs,
+/// For example:
+/// ```
+/// use gsrs::*;
+/// struct Words<'a>(Vec<&'a str>);
+/// deref_with_lifetime!(Words);
+/// impl<'a> UserIter<'a> for Words<'a> {
+///     type Item = str;
+///     fn user_refs(&self) -> &[&'a str] {
+///         &self.0
+///     }
+/// }
+/// let srs = SRS::<_, Words>::create_with("a b".to_owned(), |owner| Words(owner.split(' ').collect()));
+/// assert_eq!(vec!["a", "b"], srs.iter().collect::<Vec<_>>());
+/// ```
+pub trait UserIter<'a> {
+    /// Referenced type
+    type Item: ?Sized + 'static;
+
+    /// Returns all references
+    fn user_refs(&self) -> &[&'a Self::Item];
+}
+
+impl<'a, T: ?Sized + 'static> UserIter<'a> for Vec<&'a T> {
+    type Item = T;
+
+    fn user_refs(&self) -> &[&'a T] {
+        self
+    }
+}
+
+/// Owners that have separate length and allocated capacity, like `String` or `Vec`
+///
+/// See `SRS::split_with_info`
+pub trait HasCapacity {
+    /// Length of the owned data
+    fn len(&self) -> usize;
+    /// Whether owned data is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Allocated capacity
+    fn capacity(&self) -> usize;
+}
+
+impl HasCapacity for String {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T> HasCapacity for Vec<T> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// Length and capacity of the owner returned by `SRS::split_with_info`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnerInfo {
+    /// Length of the owned data
+    pub len: usize,
+    /// Allocated capacity
+    pub capacity: usize,
+}
+
+/// Unsized types that can be recreated from pointer and length, so references to them can be moved to another buffer.
+/// See `SRS::relocate`
+pub trait RawParts: 'static {
+    /// Length in elements, or in bytes for `str`
+    fn raw_len(&self) -> usize;
+
+    /// Recreates reference from pointer to the first byte and `raw_len`
+    ///
+    /// # Safety
+    /// Same as for `slice::from_raw_parts`, and for `str` bytes must be valid UTF-8.
+    unsafe fn from_raw_parts<'a>(ptr: *const u8, len: usize) -> &'a Self;
+}
+
+impl RawParts for str {
+    fn raw_len(&self) -> usize {
+        self.len()
+    }
+
+    unsafe fn from_raw_parts<'a>(ptr: *const u8, len: usize) -> &'a Self {
+        core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len))
+    }
+}
+
+impl<T: 'static> RawParts for [T] {
+    fn raw_len(&self) -> usize {
+        self.len()
+    }
+
+    unsafe fn from_raw_parts<'a>(ptr: *const u8, len: usize) -> &'a Self {
+        core::slice::from_raw_parts(ptr.cast(), len)
+    }
+}
+
+/// Owners whose appending methods never move or free contents that are already present,
+/// so references to that contents stay valid while owner is extended. See `SRS::with_owner_mut`
+///
+/// For `Vec` of heap owning elements only contents of elements stays in place, elements themselves can move.
+///
+/// # Safety
+/// Appending must not move, free or modify contents already present in the owner.
+pub unsafe trait AppendOnly {}
+
+unsafe impl AppendOnly for Vec<String> {}
+unsafe impl<T> AppendOnly for Vec<Vec<T>> {}
+unsafe impl<T: ?Sized> AppendOnly for Vec<Box<T>> {}
+unsafe impl<T> AppendOnly for FixedVec<T> {}
+
+/// `Vec` that never grows past capacity it was created with, so its elements never move.
+///
+/// Appending methods refuse to add elements that don't fit instead of reallocating.
+#[derive(Debug, Default)]
+pub struct FixedVec<T> {
+    vec: Vec<T>,
+}
+
+impl<T> FixedVec<T> {
+    /// Creates empty `FixedVec` that can hold `capacity` elements
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            vec: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Number of elements it can hold
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Appends `value`, or returns it back if there is no capacity left
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.vec.len() == self.vec.capacity() {
+            return Err(value);
+        }
+        self.vec.push(value);
+        Ok(())
+    }
+
+    /// Appends clones of `items` if all of them fit, returns whether they were appended
+    pub fn extend_from_slice(&mut self, items: &[T]) -> bool
+    where
+        T: Clone,
+    {
+        if self.vec.capacity() - self.vec.len() < items.len() {
+            return false;
+        }
+        self.vec.extend_from_slice(items);
+        true
+    }
+}
+
+impl<T> Deref for FixedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.vec
+    }
+}
+
+/// Owners that can report how much heap memory they own, for memory profiling
+///
+/// Only direct allocation is counted, heap memory owned by elements themselves is not included.
+/// See `SRS::owner_heap_size`
+pub trait HeapSize {
+    /// Heap bytes owned, not counting size of `Self`
+    fn heap_size(&self) -> usize;
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>()
+    }
+}
+
+// arena doesn't expose its capacity so only allocated elements are counted
+#[cfg(feature = "arena")]
+impl<T> HeapSize for typed_arena::Arena<T> {
+    fn heap_size(&self) -> usize {
+        self.len() * mem::size_of::<T>()
+    }
+}
+
+/// Splits owner into string references, see `SRS::from_tokenizer`
+///
+/// `Whitespace`, `Lines` and `Chars` are provided for string owners.
+pub trait Tokenizer<Owner: ?Sized> {
+    /// Returns tokens of `owner`
+    fn tokenize<'a>(&self, owner: &'a Owner) -> Vec<&'a str>;
+}
+
+/// Splits by whitespace
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Whitespace;
+
+/// Splits by lines
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lines;
+
+/// Splits into separate chars
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chars;
+
+impl<Owner: AsRef<str> + ?Sized> Tokenizer<Owner> for Whitespace {
+    fn tokenize<'a>(&self, owner: &'a Owner) -> Vec<&'a str> {
+        owner.as_ref().split_whitespace().collect()
+    }
+}
+
+impl<Owner: AsRef<str> + ?Sized> Tokenizer<Owner> for Lines {
+    fn tokenize<'a>(&self, owner: &'a Owner) -> Vec<&'a str> {
+        owner.as_ref().lines().collect()
+    }
+}
+
+impl<Owner: AsRef<str> + ?Sized> Tokenizer<Owner> for Chars {
+    fn tokenize<'a>(&self, owner: &'a Owner) -> Vec<&'a str> {
+        let text = owner.as_ref();
+        text.char_indices().map(|(i, c)| &text[i..i + c.len_utf8()]).collect()
+    }
+}
+
+/// Owners that can be viewed as bytes, see `SRS::owner_bytes`
+pub trait AsOwnerBytes {
+    /// Byte view of the owner
+    fn owner_bytes(&self) -> &[u8];
+}
+
+impl AsOwnerBytes for String {
+    fn owner_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsOwnerBytes for Vec<u8> {
+    fn owner_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl AsOwnerBytes for [u8] {
+    fn owner_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Owners that can report memory ranges they own, used by `SRS::check_invariants`
+pub trait OwnsRange {
+    /// Address range of the owned data
+    fn owned_range(&self) -> Range<usize>;
+}
+
+impl OwnsRange for String {
+    fn owned_range(&self) -> Range<usize> {
+        let start = self.as_ptr() as usize;
+        start..start + self.len()
+    }
+}
+
+impl<T> OwnsRange for Vec<T> {
+    fn owned_range(&self) -> Range<usize> {
+        let start = self.as_ptr() as usize;
+        start..start + self.len() * mem::size_of::<T>()
+    }
+}
+
+/// This trait should be implemented for any struct that will contain references to data inside `SRS`
+/// and it should be implemented for any lifetime.
+/// Basically it just allows to apply custom lifetime to struct
+///
+/// It is already implemented for pure references.
+/// In general `deref_with_lifetime' macro should be used to implement this trait safely.
+///
+/// # Safety
+/// It is unsafe because SRS expects implementations of this trait to only change lifetime.
+///
+/// TODO this will only be implemented with macro in future
+pub unsafe trait DerefWithLifetime<'a> {
+    /// Implementors should make `Target` a Self but generic over `'a`, see macro definition
+    type Target: 'a;
+    // type Static: 'static;
+    /// implementation should be just `transmute(self)` to only change lifetime
+    ///
+    /// # Safety
+    /// Same as for the trait itself, resulting lifetime is not checked
+    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target;
+
+    /// implementation should be just `transmute(self)` to only change lifetime
+    ///
+    /// # Safety
+    /// Same as for the trait itself, resulting lifetime is not checked
+    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target;
+
+    /// implementation should be just `transmute(self)` to only change lifetime
+    ///
+    /// # Safety
+    /// Same as for the trait itself, resulting lifetime is not checked
+    unsafe fn move_with_lifetime(self) -> Self::Target;
+
+    /// implementation should be just `transmute(self)` to only change lifetime
+    ///
+    /// # Safety
+    /// Same as for the trait itself, resulting lifetime is not checked
+    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self;
+
+    // unsafe fn move_as_static(self) -> Self::Static;
+}
+
+// with `checked` feature lifetimes are changed with reborrows through raw pointers instead of `transmute`,
+// it is the same for compiler, but easier to audit, because types can't be changed by accident
+#[cfg(not(feature = "checked"))]
+unsafe impl<'a, Z: ?Sized + 'static> DerefWithLifetime<'a> for &'_ Z {
+    type Target = &'a Z;
+
+    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target {
+        core::mem::transmute(self)
+    }
+
+    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target {
+        core::mem::transmute(self)
+    }
+
+    unsafe fn move_with_lifetime(self) -> Self::Target {
+        core::mem::transmute(self)
+    }
+
+    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+        core::mem::transmute(this)
+    }
+}
+
+#[cfg(feature = "checked")]
+unsafe impl<'a, Z: ?Sized + 'static> DerefWithLifetime<'a> for &'_ Z {
+    type Target = &'a Z;
+
+    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target {
+        &*(self as *const Self).cast::<Self::Target>()
+    }
+
+    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target {
+        &mut *(self as *mut Self).cast::<Self::Target>()
+    }
+
+    unsafe fn move_with_lifetime(self) -> Self::Target {
+        &*(self as *const Z)
+    }
+
+    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+        &*(this as *const Z)
+    }
+}
+
+/// Not a public API, used by `deref_with_lifetime` macro
+#[doc(hidden)]
+pub mod __private {
+    use core::marker::PhantomData;
+    use core::mem::size_of;
+
+    struct SameSize<A, B>(PhantomData<(A, B)>);
+
+    impl<A, B> SameSize<A, B> {
+        // evaluated during monomorphization, so mismatch is a compile error
+        const CHECK: () = assert!(size_of::<A>() == size_of::<B>(), "cast_lifetime changes size");
+    }
+
+    // `transmute` can't be used in generic impls because compiler can't prove that sizes are equal,
+    // but only lifetimes are changed, so layout is the same anyway
+    #[inline(always)]
+    #[allow(clippy::let_unit_value)]
+    pub unsafe fn cast_lifetime<A, B>(this: A) -> B {
+        let () = SameSize::<A, B>::CHECK;
+        let this = core::mem::ManuallyDrop::new(this);
+        core::ptr::read(&*this as *const A as *const B)
+    }
+}
+use __private::cast_lifetime;
+
+unsafe impl<'a, T> DerefWithLifetime<'a> for Vec<T>
+where
+    T: DerefWithLifetime<'a>,
+{
+    type Target = Vec<T::Target>;
+
+    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target {
+        &*(self as *const Self as *const Self::Target)
+    }
+
+    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target {
+        &mut *(self as *mut Self as *mut Self::Target)
+    }
+
+    unsafe fn move_with_lifetime(self) -> Self::Target {
+        cast_lifetime(self)
+    }
+
+    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+        cast_lifetime(this)
+    }
+}
+
+#[cfg(feature = "std")]
+unsafe impl<'a, K: 'static, V, S: 'static> DerefWithLifetime<'a> for HashMap<K, V, S>
+where
+    V: DerefWithLifetime<'a>,
+{
+    type Target = HashMap<K, V::Target, S>;
+
+    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target {
+        &*(self as *const Self as *const Self::Target)
+    }
+
+    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target {
+        &mut *(self as *mut Self as *mut Self::Target)
+    }
+
+    unsafe fn move_with_lifetime(self) -> Self::Target {
+        cast_lifetime(self)
+    }
+
+    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+        cast_lifetime(this)
+    }
+}
+
+// keys can borrow from owner, changing their lifetime doesn't change their ordering
+unsafe impl<'a, K, V: 'static> DerefWithLifetime<'a> for BTreeMap<K, V>
+where
+    K: DerefWithLifetime<'a>,
+{
+    type Target = BTreeMap<K::Target, V>;
+
+    unsafe fn deref_with_lifetime(&'a self) -> &'a Self::Target {
+        &*(self as *const Self as *const Self::Target)
+    }
+
+    unsafe fn deref_with_lifetime_mut(&'a mut self) -> &'a mut Self::Target {
+        &mut *(self as *mut Self as *mut Self::Target)
+    }
+
+    unsafe fn move_with_lifetime(self) -> Self::Target {
+        cast_lifetime(self)
+    }
+
+    unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+        cast_lifetime(this)
+    }
+}
+
+/// Macro to implement `DerefWithLifetime`
+///
+/// Struct name alone works for structs with exactly one lifetime,
+/// in future this will be the only way to implement trait
+///
+/// Structs with type parameters or several lifetimes are supported if generics are listed explicitly.
+/// Type parameters get `'static` bound and all lifetimes are unified into the single lifetime of `SRS`:
+/// ```
+/// use gsrs::*;
+/// struct MyRef<'a, T>(Vec<&'a T>);
+/// deref_with_lifetime!(MyRef<'a, T>);
+/// let srs = SRS::<_, MyRef<u8>>::create_with(vec![1u8, 2], |owner| MyRef(owner.iter().collect()));
+///
+/// struct TwoRefs<'a, 'b>(&'a str, &'b [u8]);
+/// deref_with_lifetime!(TwoRefs<'a, 'b>);
+/// let srs = SRS::<_, TwoRefs>::create_with(("a".to_owned(), vec![1]), |owner| TwoRefs(&owner.0, &owner.1));
+/// ```
+///
+/// Several structs with single lifetime can be listed at once:
+/// ```
+/// use gsrs::*;
+/// struct A<'a>(&'a str);
+/// struct B<'a>(&'a [u8]);
+/// deref_with_lifetime!(A, B);
+/// ```
+///
+/// Bounds on type parameters can be added with `where` after generics:
+/// ```
+/// use gsrs::*;
+/// struct Sorted<'a, T: Ord>(&'a [T]);
+/// deref_with_lifetime!(Sorted<'a, T> where T: Ord);
+/// ```
+///
+/// `deref_with_lifetime!(TestRef as pub TestRefStatic)` also creates `type TestRefStatic = TestRef<'static>`
+/// alias to be used as `SRS` type parameter:
+/// ```
+/// use gsrs::*;
+/// struct TestRef<'a>(&'a str);
+/// deref_with_lifetime!(TestRef as TestRefStatic);
+/// let srs = SRS::<String, TestRefStatic>::create_with("test".to_owned(), |owner| TestRef(owner));
+/// ```
+#[macro_export]
+macro_rules! deref_with_lifetime {
+    // generic arguments are munched one by one into parameters of impl, arguments of `Self`
+    // and arguments of `Target`, every lifetime is replaced by single lifetime of `SRS`
+    (@generics $struct: ident [$($params: tt)*] [$($self_args: tt)*] [$($target_args: tt)*]
+        $lt: lifetime , $($rest: tt)*) => {
+        $crate::deref_with_lifetime!(@generics $struct [$($params)*] [$($self_args)* '_,]
+            [$($target_args)* '__srs,] $($rest)*);
+    };
+    (@generics $struct: ident [$($params: tt)*] [$($self_args: tt)*] [$($target_args: tt)*]
+        $lt: lifetime > $($rest: tt)*) => {
+        $crate::deref_with_lifetime!(@generics $struct [$($params)*] [$($self_args)* '_,]
+            [$($target_args)* '__srs,] > $($rest)*);
+    };
+    (@generics $struct: ident [$($params: tt)*] [$($self_args: tt)*] [$($target_args: tt)*]
+        $param: ident , $($rest: tt)*) => {
+        $crate::deref_with_lifetime!(@generics $struct [$($params)* $param: 'static,] [$($self_args)* $param,]
+            [$($target_args)* $param,] $($rest)*);
+    };
+    (@generics $struct: ident [$($params: tt)*] [$($self_args: tt)*] [$($target_args: tt)*]
+        $param: ident > $($rest: tt)*) => {
+        $crate::deref_with_lifetime!(@generics $struct [$($params)* $param: 'static,] [$($self_args)* $param,]
+            [$($target_args)* $param,] > $($rest)*);
+    };
+    (@generics $struct: ident [$($params: tt)*] [$($self_args: tt)*] [$($target_args: tt)*] >
+        $(where $($bounds: tt)+)?) => {
+        unsafe impl<'__srs, $($params)*> DerefWithLifetime<'__srs> for $struct<$($self_args)*>
+        $(where $($bounds)+)?
+        {
+            type Target = $struct<$($target_args)*>;
+            #[inline(always)]
+            unsafe fn deref_with_lifetime(&'__srs self) -> &'__srs Self::Target {
+                &*(self as *const Self as *const Self::Target)
+            }
+
+            #[inline(always)]
+            unsafe fn deref_with_lifetime_mut(&'__srs mut self) -> &'__srs mut Self::Target {
+                &mut *(self as *mut Self as *mut Self::Target)
+            }
+
+            #[inline(always)]
+            unsafe fn move_with_lifetime(self) -> Self::Target {
+                $crate::__private::cast_lifetime(self)
+            }
+
+            #[inline(always)]
+            unsafe fn move_with_lifetime_back(this: Self::Target) -> Self {
+                $crate::__private::cast_lifetime(this)
+            }
+        }
+    };
+    ($struct: ident < $($rest: tt)+) => {
+        $crate::deref_with_lifetime!(@generics $struct [] [] [] $($rest)+);
+    };
+    ($first: ident, $($struct: ident),+ $(,)?) => {
+        $crate::deref_with_lifetime!($first);
+        $($crate::deref_with_lifetime!($struct);)+
+    };
+    ($struct: tt as $vis: vis $alias: ident) => {
+        $crate::deref_with_lifetime!($struct);
+        $vis type $alias = $struct<'static>;
+    };
     ($struct: tt) => {
         unsafe impl<'a> DerefWithLifetime<'a> for $struct<'_> {
             type Target = $struct<'a>;
@@ -0,0 +1,94 @@
+use crate::DerefWithLifetime;
+use core::ops::Deref;
+
+/// Owner that can be shared between several referencing parts by a plain borrow.
+///
+/// Unlike `SRS` referencing parts are not bundled with the owner,
+/// so `SharedSRS` values created with `view` borrow this guard
+/// and can be moved around freely only while it is alive.
+/// In exchange there is no `Rc` and no unsafe lifetime tricks involved at all.
+#[derive(Debug, Default)]
+pub struct SharedOwner<Owner> {
+    owner: Owner,
+}
+
+impl<Owner> SharedOwner<Owner> {
+    /// Creates new guard owning `owner`
+    pub fn new(owner: Owner) -> Self {
+        Self { owner }
+    }
+
+    /// Creates new view over the owner with referencing part created by `f`
+    pub fn view<'o, U, F>(&'o self, f: F) -> SharedSRS<'o, Owner, U>
+    where
+        U: for<'b> DerefWithLifetime<'b>,
+        F: FnOnce(&'o Owner) -> <U as DerefWithLifetime<'o>>::Target,
+    {
+        SharedSRS {
+            user: f(&self.owner),
+            owner: &self.owner,
+        }
+    }
+
+    /// Returns the owner back, possible only when there are no views left
+    pub fn into_inner(self) -> Owner {
+        self.owner
+    }
+}
+
+impl<Owner> Deref for SharedOwner<Owner> {
+    type Target = Owner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.owner
+    }
+}
+
+/// Referencing part bundled with a borrow of an owner inside `SharedOwner`
+///
+/// `U` is specified the same way as for `SRS`, so the same referencing structs can be used for both.
+pub struct SharedSRS<'o, Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    user: <U as DerefWithLifetime<'o>>::Target,
+    owner: &'o Owner,
+}
+
+impl<'o, Owner, U> SharedSRS<'o, Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Referencing part of this view
+    pub fn user(&self) -> &<U as DerefWithLifetime<'o>>::Target {
+        &self.user
+    }
+
+    /// Mutable referencing part of this view, it can be extended with new references from `owner`
+    pub fn user_mut(&mut self) -> &mut <U as DerefWithLifetime<'o>>::Target {
+        &mut self.user
+    }
+
+    /// Shared owner, it lives as long as `SharedOwner` it was created from
+    pub fn owner(&self) -> &'o Owner {
+        self.owner
+    }
+
+    /// Returns referencing part, dropping the view
+    pub fn into_user(self) -> <U as DerefWithLifetime<'o>>::Target {
+        self.user
+    }
+}
+
+/// Builds referencing part over borrowed `owner` with `build` and immediately uses it with `use_`.
+///
+/// When owner already lives on the stack and references are needed only briefly
+/// there is no need for `SRS` at all: no boxing and no `DerefWithLifetime`, lifetimes are natural.
+pub fn analyze<'o, Owner, T, R, B, F>(owner: &'o Owner, build: B, use_: F) -> R
+where
+    Owner: ?Sized,
+    B: FnOnce(&'o Owner) -> T,
+    F: FnOnce(&T) -> R,
+{
+    use_(&build(owner))
+}
@@ -0,0 +1,83 @@
+use crate::DerefWithLifetime;
+use core::marker::PhantomPinned;
+use core::mem::ManuallyDrop;
+use core::pin::Pin;
+
+/// `SRS` with owner stored inline instead of in a separate heap allocation.
+///
+/// This one is the most efficient but most restrictive.
+/// Since references point inside the struct itself it can't be moved after they are created,
+/// so it is used through `Pin`, e.g. after `std::pin::pin!` on the stack or `Box::pin`.
+/// If you need to move it around after it was referenced use `SRS`.
+#[derive(Debug)]
+pub struct SRSThin<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    user: ManuallyDrop<U>,
+    owner: Owner,
+    // also makes `&mut SRSThin` not `noalias`, because references in `user` point into `owner`
+    _pinned: PhantomPinned,
+}
+
+impl<Owner, U: Default> SRSThin<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Creates new instance without any self references,
+    /// they can be added with `with` after it is pinned
+    pub fn new(owner: Owner) -> Self {
+        Self {
+            user: Default::default(),
+            owner,
+            _pinned: PhantomPinned,
+        }
+    }
+}
+
+impl<Owner, U> SRSThin<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    /// Same as `SRS::with`
+    #[inline]
+    pub fn with<'b, F, Z: 'static>(self: Pin<&'b mut Self>, f: F) -> Z
+    where
+        for<'x> F: 'static + FnOnce(&'x mut <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> Z,
+    {
+        // nothing is moved out
+        let this = unsafe { self.get_unchecked_mut() };
+        let user = unsafe { this.user.deref_with_lifetime_mut() };
+        f(user, &this.owner)
+    }
+
+    /// Same as `SRS::get_ref`
+    ///
+    /// It doesn't require `Pin` because references could be created only by `with` after pinning,
+    /// and pinned value is available by shared reference anyway
+    #[inline]
+    pub fn get_ref<'b, F, Z: ?Sized + 'static>(&'b self, f: F) -> &'b Z
+    where
+        for<'x> F: 'static + FnOnce(&'x <U as DerefWithLifetime<'b>>::Target, &'b Owner) -> &'b Z,
+    {
+        let user = unsafe { self.user.deref_with_lifetime() };
+        f(user, &self.owner)
+    }
+
+    /// Owner
+    #[inline]
+    pub fn owner(&self) -> &Owner {
+        &self.owner
+    }
+}
+
+// references have to be destroyed before the data they point into,
+// so `user` is dropped explicitly and owner is dropped afterwards as a field
+impl<Owner, U> Drop for SRSThin<Owner, U>
+where
+    U: for<'b> DerefWithLifetime<'b>,
+{
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.user) }
+    }
+}
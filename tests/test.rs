@@ -1,13 +1,26 @@
 // #![feature(test)]
+// baseline tests are kept as they were written
+#![allow(unused_mut, clippy::redundant_closure, clippy::explicit_auto_deref)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 use gsrs::deref_with_lifetime;
 use gsrs::DerefWithLifetime;
 use gsrs::SRS;
+use gsrs::LazySRS;
 
 use std::cmp::Ordering;
 use std::cell::Cell;
 use std::ops::Deref;
 
+#[derive(Clone, Default)]
+struct Words<'a>(Vec<&'a str>);
+deref_with_lifetime!(Words);
+
+/// `SRS` over `text` split by spaces
+fn words(text: &str) -> SRS<String, Words<'static>> {
+    SRS::create_with(text.to_owned(), |owner| Words(owner.split(' ').collect()))
+}
+
 #[test]
 fn test_create_with_and_get_ref() {
     use gsrs::*;
@@ -96,6 +109,33 @@ fn test_string_suffix_array() {
     assert!(a && c && !b);
 }
 
+#[test]
+fn test_iter_suffix_array() {
+    struct TestRef<'a>(Vec<&'a str>);
+    deref_with_lifetime!(TestRef);
+    impl<'a> gsrs::UserIter<'a> for TestRef<'a> {
+        type Item = str;
+        fn user_refs(&self) -> &[&'a str] {
+            &self.0
+        }
+    }
+
+    let suffix_array = SRS::<_, TestRef>::create_with("abca".to_owned(), |owner| {
+        let mut vec = (0..owner.len()).map(|i| &owner[i..]).collect::<Vec<_>>();
+        vec.sort();
+        TestRef(vec)
+    });
+    let suffixes = suffix_array.iter().collect::<Vec<_>>();
+    assert_eq!(vec!["a", "abca", "bca", "ca"], suffixes);
+    assert!(suffixes.iter().all(|it| suffix_array.as_bytes().as_ptr_range().contains(&it.as_ptr())));
+    let mut count = 0;
+    for suffix in &suffix_array {
+        assert!(suffix.ends_with('a'));
+        count += 1;
+    }
+    assert_eq!(4, count);
+}
+
 #[test]
 fn test_cell() {
     struct TestRef<'a>(&'a Cell<u8>);
@@ -125,27 +165,1193 @@ fn test_cell_raw_ref() {
     assert_eq!(res, 20);
 }
 
-// this should never be able to compile
-// todo check this with trybuild crate
-// #[test]
-// fn test_cell_user_ref() {
-//     #[derive(Default)]
-//     struct User<'a>(Cell<Option<&'a User<'a>>>);
-//     deref_with_lifetime!(User);
-//     // fn helper(srs: Box<SRS<(),User>>)  {
-//     //     srs.get(|user|println!("{:p}",user.0.get().unwrap() as *const _))
-//     // }
-//
-//     let mut srs = SRS::<(), User>::default();
-//     let mut srs2 = SRS::<(), User>::default();
-//     srs.get(|user|{
-//         user.0.set(Some(user))
-//     });
-//     let before = srs.get(|user|user.0.get().unwrap() as *const _ as usize);
-//     core::mem::swap(&mut srs,&mut srs2);
-//     let after = srs.get(|user|user.0.get().unwrap() as *const _ as usize);
-//     assert_eq!(before,after);
-// }
+#[test]
+fn test_get_cloned() {
+    struct TestRef<'a>(Vec<&'a String>);
+    deref_with_lifetime!(TestRef);
+
+    let srs = SRS::<_, TestRef>::create_with(
+        vec!["first".to_owned(), "second".to_owned()],
+        |owner| TestRef(owner.iter().rev().collect()),
+    );
+    let first = srs.get_cloned(|user, _| user.0[1]);
+    drop(srs);
+    assert_eq!("first", first);
+}
+
+#[test]
+fn test_rebuild_range() {
+    let mut srs = words("aaa bbb ccc");
+    let before = srs.with(|user, _| user.0.iter().map(|it| it.as_ptr() as usize).collect::<Vec<_>>());
+    unsafe {
+        srs.rebuild_range(
+            4..7,
+            |user| &mut user.0,
+            |owner| {
+                let middle = owner.as_mut_vec().as_mut_ptr().add(4);
+                std::slice::from_raw_parts_mut(middle, 3).copy_from_slice(b"x y");
+            },
+            |owner, range| owner[range].split(' ').collect(),
+        );
+    }
+    let after = srs.with(|user, _| user.0.iter().map(|it| it.as_ptr() as usize).collect::<Vec<_>>());
+    assert_eq!(4, after.len());
+    assert_eq!(before[0], after[0]);
+    assert_eq!(before[2], after[3]);
+    assert!(srs.with(|user, _| user.0 == ["aaa", "x", "y", "ccc"]));
+    assert_eq!("aaa x y ccc", srs.as_str());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_get_by_key() {
+    use std::collections::HashMap;
+
+    let srs = SRS::<_, HashMap<String, &'static str>>::create_with(
+        "name=alice;city=paris".to_owned(),
+        |owner| {
+            owner
+                .split(';')
+                .map(|it| {
+                    let mut pair = it.splitn(2, '=');
+                    (pair.next().unwrap().to_owned(), pair.next().unwrap())
+                })
+                .collect()
+        },
+    );
+    let srs = Box::new(srs);
+    assert_eq!(Some("paris"), srs.get_by_key("city", |user| user));
+    assert_eq!(None, srs.get_by_key("country", |user| user));
+}
+
+#[test]
+fn test_query() {
+    fn avg_len(srs: &SRS<String, Words>) -> usize {
+        srs.query(|user, owner| (owner.len() - user.0.len() + 1) / user.0.len())
+    }
+
+    let srs = words("one two three four");
+    assert_eq!(3, avg_len(&srs));
+}
+
+#[test]
+fn test_into_cloned_iter() {
+    struct Names<'a>(Vec<&'a String>);
+    deref_with_lifetime!(Names);
+
+    let srs = SRS::<_, Names>::create_with(
+        vec!["one".to_owned(), "two".to_owned(), "three".to_owned()],
+        |owner| Names(owner.iter().filter(|it| it.starts_with('t')).collect()),
+    );
+    let words = srs.into_cloned_iter(|user| &user.0).collect::<Vec<String>>();
+    assert_eq!(vec!["two".to_owned(), "three".to_owned()], words);
+}
+
+#[test]
+fn test_lines() {
+    let srs = SRS::lines("first line\nsecond line\r\nthird line".to_owned());
+    let moved = Box::new(srs);
+    assert_eq!(Some("second line"), moved.line(1));
+    assert_eq!(Some("third line"), moved.line(2));
+    assert_eq!(None, moved.line(3));
+}
+
+#[test]
+fn test_ref_count() {
+    let srs = words("one two three");
+    assert_eq!(3, srs.ref_count(|user| user.0.len()));
+
+    let srs = SRS::<_, Vec<&str>>::create_with("one two three four".to_owned(), |owner| {
+        owner.split(' ').collect()
+    });
+    assert_eq!(4, srs.user_len());
+}
+
+#[test]
+fn test_indexed_search() {
+    struct Record {
+        id: u32,
+        name: &'static str,
+    }
+
+    let records = vec![
+        Record { id: 30, name: "c" },
+        Record { id: 10, name: "a" },
+        Record { id: 20, name: "b" },
+    ];
+    let srs = SRS::indexed(records, |it| it.id);
+    let moved = Box::new(srs);
+    assert_eq!("b", moved.search(&20, |it| it.id).unwrap().name);
+    assert!(moved.search(&40, |it| it.id).is_none());
+}
+
+#[test]
+fn test_concat() {
+    let srs = words("one two").concat(words("three four"), |a, b| format!("{} {}", a, b), |owner| {
+        Words(owner.split(' ').collect())
+    });
+    assert_eq!(4, srs.ref_count(|user| user.0.len()));
+    assert_eq!("three", srs.get_ref(|user, _| user.0[2]));
+    let (start, end) = (srs.as_ptr() as usize, srs.as_ptr() as usize + srs.len());
+    assert!(srs.query(move |user, _| {
+        user.0.iter().all(|it| (start..end).contains(&(it.as_ptr() as usize)))
+    }));
+}
+
+#[test]
+fn test_ref_ptrs() {
+    let srs = words("one two three");
+    let ptrs = srs.ref_ptrs(|user| user.0.iter().map(|it| it.as_ptr() as *const ()).collect());
+    let owner = srs.as_ptr() as *const ();
+    assert_eq!(3, ptrs.len());
+    assert!(ptrs
+        .iter()
+        .all(|&ptr| ptr >= owner && (ptr as usize) < owner as usize + srs.len()));
+}
+
+#[test]
+fn test_lazy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+    let srs = LazySRS::<_, Words>::new("one two".to_owned(), |owner| {
+        BUILDS.fetch_add(1, Ordering::SeqCst);
+        Words(owner.split(' ').collect())
+    });
+    assert!(!srs.is_built());
+    assert_eq!(0, BUILDS.load(Ordering::SeqCst));
+    assert_eq!("two", srs.get_ref(|user, _| user.0[1]));
+    let mut srs = Box::new(srs);
+    assert_eq!(2, srs.with(|user, _| user.0.len()));
+    assert!(srs.is_built());
+    assert_eq!(1, BUILDS.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_index() {
+    let srs = SRS::<_, Vec<&str>>::create_with("zero one two".to_owned(), |owner| {
+        owner.split(' ').collect()
+    });
+    let srs = Box::new(srs);
+    assert_eq!("one", &srs[1]);
+    assert_eq!("two", &srs[2]);
+}
+
+// this and other tests should behave the same with and without `checked` feature,
+// it is also meant to be run under miri: `cargo +nightly miri test --features checked`
+#[test]
+fn test_lifetime_casts() {
+    struct TestRef<'a>(&'a [u8], &'a u8);
+    deref_with_lifetime!(TestRef);
+
+    let srs = SRS::<_, &'static [u8]>::create_with(vec![1, 2, 3], |owner| &owner[1..]);
+    let expected = srs.as_ptr() as usize + 1;
+    let mut moved = Box::new(srs);
+    assert_eq!(expected, moved.get_ref(|user, _| *user).as_ptr() as usize);
+    assert_eq!(&[2, 3], moved.with(|user, _| user.to_vec()).as_slice());
+
+    let srs = SRS::<_, TestRef>::create_with(vec![1, 2, 3], |owner| TestRef(owner, &owner[2]));
+    assert_eq!(3, *srs.get_ref(|user, _| user.1));
+    let mut placeholder = Box::new(Vec::new());
+    let user = srs.split(&mut placeholder);
+    assert_eq!(&[1, 2, 3], user.0);
+}
+
+#[test]
+fn test_split_with_info() {
+    let mut owner = Vec::with_capacity(16);
+    owner.extend_from_slice(&[1u8, 2, 3, 4]);
+    let srs = SRS::<_, &'static [u8]>::create_with(owner, |owner| &owner[..2]);
+    let mut reused = Box::new(Vec::new());
+    let (user, info) = srs.split_with_info(&mut reused);
+    assert_eq!(&[1, 2], user);
+    assert_eq!(4, info.len);
+    assert!(info.capacity >= 16);
+    assert_eq!(info.capacity, reused.capacity());
+}
+
+#[test]
+fn test_btree_range() {
+    use std::collections::BTreeMap;
+    let srs = SRS::<_, BTreeMap<&'static str, usize>>::create_with(
+        "b a c b d a b".to_owned(),
+        |owner| {
+            let mut counts = BTreeMap::new();
+            for word in owner.split(' ') {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+            counts
+        },
+    );
+    let srs = Box::new(srs);
+    let found: Vec<_> = srs.range("b".."d").collect();
+    assert_eq!(vec![("b", &3), ("c", &1)], found);
+}
+
+#[test]
+fn test_export_ffi_table() {
+    let srs = words("one two three");
+    let table = srs.export_ffi_table(|owner| (owner.as_ptr(), owner.len()), |user| &user.0);
+    assert_eq!(vec![[0, 3], [4, 3], [8, 5]], table);
+    let words: Vec<_> = table
+        .iter()
+        .map(|&[start, len]| &srs[start as usize..(start + len) as usize])
+        .collect();
+    assert_eq!(vec!["one", "two", "three"], words);
+}
+
+#[test]
+fn test_export_ffi_table_u16() {
+    let srs = SRS::<_, Vec<&'static [u16]>>::create_with(vec![1u16, 2, 3, 4], |owner| vec![&owner[1..3]]);
+    let table = srs.export_ffi_table(|owner| (owner.as_ptr().cast(), owner.len() * 2), |user| user.as_slice());
+    assert_eq!(vec![[2, 4]], table);
+}
+
+#[test]
+fn test_into_bundle() {
+    let srs = words("one two three");
+    let (owner, lens): (String, Vec<usize>) =
+        srs.into_bundle(|user, _| user.0.iter().map(|it| it.len()).collect());
+    assert_eq!("one two three", owner);
+    assert_eq!(vec![3, 3, 5], lens);
+}
+
+#[test]
+fn test_from_pinned_box() {
+    use std::marker::PhantomPinned;
+    use std::pin::Pin;
+    struct Node {
+        name: String,
+        _pinned: PhantomPinned,
+    }
+    struct NameRef<'a>(&'a str);
+    deref_with_lifetime!(NameRef);
+
+    let node = Box::pin(Node { name: "root".to_owned(), _pinned: PhantomPinned });
+    let addr = &*node as *const Node;
+    let srs = SRS::<_, NameRef>::from_pinned_box(node, |node| NameRef(&node.name[1..]));
+    let moved = vec![srs];
+    assert_eq!("oot", moved[0].get_ref(|user, _| user.0));
+    let srs = moved.into_iter().next().unwrap();
+    let mut slot = Box::new(Box::pin(Node { name: String::new(), _pinned: PhantomPinned }));
+    let user = srs.split(&mut slot);
+    assert_eq!("oot", user.0);
+    let node: &Pin<Box<Node>> = &slot;
+    assert_eq!(addr, &**node as *const Node);
+
+    let srs = SRS::<_, NameRef>::from_pinned_box(*slot, |node| NameRef(&node.name));
+    let node = srs.into_pinned_owner();
+    assert_eq!(addr, &*node as *const Node);
+}
+
+#[test]
+fn test_relocate() {
+    // second buffer is a bitwise copy of the first one, both are owned by srs
+    let text = "one two three".to_owned();
+    let copy = String::from_utf8(text.as_bytes().to_vec()).unwrap();
+    let mut srs = SRS::<_, Words>::create_with((text, copy), |owner| {
+        Words(owner.0.split(' ').collect())
+    });
+    let old_base = srs.0.as_ptr();
+    let new_base = srs.1.as_ptr();
+    unsafe { srs.relocate(old_base, new_base, srs.0.len(), |user| &mut user.0) };
+    let mut srs = Box::new(srs);
+    let new_end = new_base as usize + srs.1.len();
+    let words = srs.with(|user, _| {
+        user.0.iter().map(|it| (it.to_string(), it.as_ptr() as usize)).collect::<Vec<_>>()
+    });
+    assert_eq!(vec!["one", "two", "three"], words.iter().map(|it| &it.0).collect::<Vec<_>>());
+    assert!(words.iter().all(|&(_, ptr)| ptr >= new_base as usize && ptr < new_end));
+}
+
+#[test]
+#[should_panic(expected = "reference is outside of the relocated bytes")]
+fn test_relocate_outside() {
+    let text = "one two".to_owned();
+    let copy = text.clone();
+    let mut srs = SRS::<_, Vec<&'static str>>::create_with((text, copy), |owner| owner.0.split(' ').collect());
+    let old_base = srs.0.as_ptr();
+    let new_base = srs.1.as_ptr();
+    // "two" is not inside of the first 3 bytes
+    unsafe { srs.relocate(old_base, new_base, 3, |user| user.as_mut_slice()) };
+}
+
+#[test]
+fn test_sort_user_by() {
+    let mut srs = words("three one four");
+    srs.sort_user_by(|user| &mut user.0, |it| it.len());
+    let mut srs = Box::new(srs);
+    assert_eq!("one four three", srs.with(|user, _| user.0.join(" ")));
+}
+
+#[test]
+fn test_sort_user_by_slices() {
+    let mut srs = SRS::<_, Vec<&'static [u8]>>::create_with(vec![3, 1, 2], |owner| owner.chunks(1).collect());
+    srs.sort_user_by(|user| user, |it| it[0]);
+    assert!(srs.with(|user, _| *user == [&[1][..], &[2], &[3]]));
+}
+
+#[test]
+fn test_get_two() {
+    struct Views<'a>(&'a [u8], &'a [u8]);
+    deref_with_lifetime!(Views);
+
+    let srs = SRS::<_, Views>::create_with(vec![1, 2, 3, 4], |owner| Views(&owner[..2], &owner[2..]));
+    let srs = Box::new(srs);
+    let (head, tail) = srs.get_two(|user, _| user.0, |user, _| user.1);
+    assert_eq!(&[1, 2], head);
+    assert_eq!(&[3, 4], tail);
+}
+
+#[test]
+fn test_analyze() {
+    let text = "one two three two";
+    let count = gsrs::analyze(text, |text| text.split(' ').collect::<Vec<_>>(), |words| {
+        words.iter().filter(|&&it| it == "two").count()
+    });
+    assert_eq!(2, count);
+}
+
+#[test]
+fn test_owner_heap_size() {
+    let srs = SRS::<_, &'static str>::create_with(String::with_capacity(64), |owner| &owner[..]);
+    assert_eq!(64, srs.owner_heap_size());
+    let srs = SRS::<_, &'static [u32]>::create_with(vec![1u32, 2, 3, 4], |owner| &owner[..]);
+    assert_eq!(16, srs.owner_heap_size());
+}
+
+#[test]
+fn test_try_fold_refs() {
+    let srs = words("one two stop three");
+    let total = srs.try_fold_refs(0, |user| &user.0, |acc, it: &str| {
+        if it == "stop" {
+            Err(acc)
+        } else {
+            Ok(acc + it.len())
+        }
+    });
+    assert_eq!(Err(6), total);
+    let total: Result<_, ()> = srs.try_fold_refs(0, |user| &user.0, |acc, it: &str| Ok(acc + it.len()));
+    assert_eq!(Ok(15), total);
+}
+
+#[test]
+fn test_tuple_owner() {
+    struct Refs<'a> {
+        name: &'a str,
+        data: &'a [u8],
+    }
+    deref_with_lifetime!(Refs);
+
+    let srs = SRS::<_, Refs>::create_with(("name: value".to_owned(), vec![1u8, 2, 3]), |(text, bytes)| Refs {
+        name: &text[..4],
+        data: &bytes[1..],
+    });
+    let moved = Box::new(srs);
+    assert_eq!("name", moved.get_ref(|user, _| user.name));
+    assert_eq!(&[2, 3], moved.get_ref(|user, _| user.data));
+}
+
+#[test]
+fn test_refs_guard() {
+    let srs = words("one two three");
+    let srs = Box::new(srs);
+    let total = srs.refs(|guard| {
+        let mut total = 0;
+        for i in 0..guard.user().0.len() {
+            assert!(guard.owner().contains(guard.user().0[i]));
+            total += guard.user().0[i].len();
+        }
+        total
+    });
+    assert_eq!(11, total);
+}
+
+#[test]
+fn test_create_with_range() {
+    let srs = SRS::<_, &'static [u8]>::create_with_range(vec![1u8, 2, 3, 4, 5], 1..3, |owner, range| &owner[range]);
+    let srs = Box::new(srs.unwrap());
+    assert_eq!(&[2, 3], srs.get_ref(|user, _| *user));
+
+    let srs = SRS::<_, &'static str>::create_with_range("abc".to_owned(), 2..4, |owner, range| &owner[range]);
+    assert_eq!("abc", srs.unwrap_err());
+}
+
+#[test]
+fn test_transact() {
+    let mut srs = words("one two three");
+    let result = srs.transact(|user, owner| {
+        user.0.clear();
+        user.0.push(&owner[..3]);
+        Err("failed")
+    });
+    assert_eq!(Err("failed"), result);
+    assert_eq!(3, srs.with(|user, _| user.0.len()));
+
+    let result: Result<(), ()> = srs.transact(|user, _| {
+        user.0.pop();
+        Ok(())
+    });
+    assert_eq!(Ok(()), result);
+    assert_eq!(2, srs.with(|user, _| user.0.len()));
+}
+
+#[test]
+fn test_char_boundaries() {
+    let srs = SRS::<_, Words>::create_with("こんにちは world".to_owned(), |owner| {
+        Words(vec![&owner[3..9], &owner[16..]])
+    });
+    srs.debug_assert_char_boundaries(|user| &user.0);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "outside of the owner")]
+fn test_char_boundaries_outside() {
+    // slices a different string instead of the owner
+    let srs = SRS::<_, Words>::create_with("こんにちは".to_owned(), |_| Words(vec!["こん"]));
+    srs.debug_assert_char_boundaries(|user| &user.0);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "reference at 1..3 is not on char boundaries")]
+fn test_char_boundaries_mid_char() {
+    // starts and ends inside of 'こ', like a reference broken by unsafe code
+    let srs = SRS::<_, Words>::create_with("こんにちは".to_owned(), |owner| {
+        Words(vec![unsafe { std::str::from_utf8_unchecked(&owner.as_bytes()[1..3]) }])
+    });
+    srs.debug_assert_char_boundaries(|user| &user.0);
+}
+
+#[test]
+fn test_from_tokenizer() {
+    use gsrs::{Chars, Tokenizer, Whitespace};
+    struct Commas;
+    impl Tokenizer<String> for Commas {
+        fn tokenize<'a>(&self, owner: &'a String) -> Vec<&'a str> {
+            owner.split(',').collect()
+        }
+    }
+
+    let srs = SRS::from_tokenizer("a,bc,d".to_owned(), Commas);
+    let srs = Box::new(srs);
+    assert_eq!(3, srs.user_len());
+    assert_eq!("bc", &srs[1]);
+    let srs = SRS::from_tokenizer("a  b\nc".to_owned(), Whitespace);
+    assert_eq!(3, srs.user_len());
+    let srs = SRS::from_tokenizer("こんa".to_owned(), Chars);
+    assert_eq!(Some("ん"), srs.line(1));
+}
+
+#[test]
+fn test_from_boxed_vec() {
+    struct First<'a>(&'a String);
+    deref_with_lifetime!(First);
+
+    let mut srs = SRS::<_, First>::from_boxed_vec(vec![Box::new("first".to_owned())], |items| First(items[0]));
+    let addr = srs.get_ref(|user, _| user.0) as *const String;
+    // forces reallocation of the outer `Vec`
+    for i in 0..100 {
+        unsafe { srs.push_boxed(Box::new(i.to_string())) };
+    }
+    let srs = Box::new(srs);
+    assert_eq!(addr, srs.get_ref(|user, _| user.0) as *const String);
+    assert_eq!("first", srs.get_ref(|user, _| user.0));
+    assert_eq!(101, srs.len());
+}
+
+#[test]
+fn test_refs_eq() {
+    let offsets: fn(&Words, &String) -> Vec<(usize, usize)> = |user, owner| {
+        user.0.iter().map(|it| (it.as_ptr() as usize - owner.as_ptr() as usize, it.len())).collect()
+    };
+    let a = words("one two");
+    let mut b = words("one two");
+    assert!(a.refs_eq(&b, offsets));
+    b.with(|user, _| user.0.reverse());
+    assert!(a.refs_eq(&b, offsets));
+    b.with(|user, _| {
+        user.0.pop();
+    });
+    assert!(!a.refs_eq(&b, offsets));
+}
+
+#[tokio::test]
+async fn test_get_ref_async() {
+    let srs = words("one two");
+    let srs = Box::new(srs);
+    let len = srs
+        .get_ref_async(|user, _| {
+            let word = user.0[1];
+            async move {
+                tokio::task::yield_now().await;
+                word.len()
+            }
+        })
+        .await;
+    assert_eq!(3, len);
+}
+
+#[test]
+fn test_shrink_user_to_fit() {
+    let mut srs = SRS::<_, Words>::create_with("one two".to_owned(), |owner| {
+        let mut words = Vec::with_capacity(64);
+        words.extend(owner.split(' '));
+        Words(words)
+    });
+    assert!(srs.with(|user, _| user.0.capacity()) >= 64);
+    srs.shrink_user_to_fit(|user| &mut user.0);
+    assert_eq!(2, srs.with(|user, _| user.0.capacity()));
+}
+
+#[test]
+fn test_shrink_user_to_fit_slices() {
+    let mut srs = SRS::<_, Vec<&'static [u8]>>::create_with(vec![1u8, 2, 3, 4], |owner| {
+        let mut chunks = Vec::with_capacity(64);
+        chunks.extend(owner.chunks(2));
+        chunks
+    });
+    srs.shrink_user_to_fit(|user| user);
+    assert_eq!(2, srs.with(|user, _| user.capacity()));
+}
+
+#[test]
+fn test_owner_bytes() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let a = SRS::<_, &'static str>::create_with("same".to_owned(), |owner| &owner[1..]);
+    let b = SRS::<_, &'static str>::create_with("same".to_owned(), |owner| &owner[2..]);
+    let c = SRS::<_, &'static [u8]>::create_with(b"other".to_vec(), |owner| &owner[..]);
+    assert_eq!(b"same", a.owner_bytes());
+    assert_eq!(hash(a.owner_bytes()), hash(b.owner_bytes()));
+    assert_ne!(hash(a.owner_bytes()), hash(c.owner_bytes()));
+}
+
+#[test]
+fn test_build_refs_once() {
+    let (owner, count) = SRS::<_, Words>::build_refs_once(
+        "one two three".to_owned(),
+        |owner| Words(owner.split(' ').collect()),
+        |user, _| user.0.len(),
+    );
+    assert_eq!("one two three", owner);
+    assert_eq!(3, count);
+}
+
+#[test]
+fn test_macro_static_alias() {
+    struct Words<'a>(Vec<&'a str>);
+    deref_with_lifetime!(Words as WordsStatic);
+
+    let srs: SRS<String, WordsStatic> =
+        SRS::create_with("one two".to_owned(), |owner| Words(owner.split(' ').collect()));
+    let moved = Box::new(srs);
+    assert_eq!("two", moved.get_ref(|user, _| user.0[1]));
+}
+
+#[test]
+fn test_has_refs() {
+    let mut srs = SRS::<_, Words>::new("one two".to_owned());
+    assert!(!srs.has_refs(|user| user.0.is_empty()));
+    srs.with(|user, owner| user.0 = owner.split(' ').collect());
+    assert!(srs.has_refs(|user| user.0.is_empty()));
+}
+
+#[test]
+fn test_transform_then_build() {
+    let srs = SRS::<_, Words>::transform_then_build(
+        "One TWO".to_owned(),
+        |owner| owner.make_ascii_lowercase(),
+        |owner| Words(owner.split(' ').collect()),
+    );
+    let srs = Box::new(srs);
+    assert_eq!("two", srs.get_ref(|user, _| user.0[1]));
+}
+
+#[test]
+fn test_rev_refs() {
+    let srs = words("one two three");
+    let srs = Box::new(srs);
+    assert_eq!(vec!["three", "two", "one"], srs.rev_refs(|user| &user.0).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_rev_refs_slices() {
+    let srs = SRS::<_, Vec<&'static [u8]>>::create_with(vec![1u8, 2, 3], |owner| owner.chunks(1).collect());
+    assert_eq!(vec![&[3u8][..], &[2], &[1]], srs.rev_refs(|user| &user[..]).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "fuzzing")]
+#[test]
+fn test_check_invariants() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("one two".to_owned(), |owner| owner.split(' ').collect());
+    srs.check_invariants(|user| user.iter().map(|it| (it.as_ptr(), it.len())).collect());
+}
+
+#[cfg(feature = "fuzzing")]
+#[test]
+#[should_panic(expected = "outside of the owner range")]
+fn test_check_invariants_outside() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("one two".to_owned(), |_| vec!["other"]);
+    srs.check_invariants(|user| user.iter().map(|it| (it.as_ptr(), it.len())).collect());
+}
+
+#[test]
+fn test_macro_generic() {
+    struct Items<'a, T>(Vec<&'a T>);
+    deref_with_lifetime!(Items<'a, T>);
+
+    let srs = SRS::<_, Items<u32>>::create_with(vec![3u32, 1, 2], |owner| Items(owner.iter().filter(|&&it| it > 1).collect()));
+    let srs = Box::new(srs);
+    assert_eq!(&2, srs.get_ref(|user, _| user.0[1]));
+}
+
+#[test]
+fn test_try_create_with() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    struct Counted(String, Rc<Cell<usize>>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+    struct Num<'a>(&'a str);
+    deref_with_lifetime!(Num);
+
+    let parse: fn(&Counted) -> Result<Num, std::num::ParseIntError> =
+        |owner| owner.0.parse::<u32>().map(|_| Num(&owner.0));
+    let drops = Rc::new(Cell::new(0));
+    let srs = SRS::<_, Num>::try_create_with(Counted("42".to_owned(), drops.clone()), parse).ok().unwrap();
+    assert_eq!("42", srs.get_ref(|user, _| user.0));
+    drop(srs);
+    assert_eq!(1, drops.get());
+
+    let drops = Rc::new(Cell::new(0));
+    let (owner, _err) = SRS::<_, Num>::try_create_with(Counted("x".to_owned(), drops.clone()), parse)
+        .err()
+        .unwrap();
+    assert_eq!(0, drops.get());
+    assert_eq!("x", owner.0);
+    drop(owner);
+    assert_eq!(1, drops.get());
+}
+
+#[test]
+fn test_whole() {
+    let srs = SRS::whole(vec![1, 2, 3]);
+    let moved = Box::new(srs);
+    assert_eq!(&[1, 2, 3], moved.get_ref(|user, _| *user));
+
+    let srs = SRS::whole_str("text".to_owned());
+    let moved = Box::new(srs);
+    assert_eq!("text", moved.get_ref(|user, _| *user));
+}
+
+#[test]
+fn test_get() {
+    let srs = words("one two");
+    let srs = Box::new(srs);
+    assert!(srs.get(|user| user.0.contains(&"two")));
+    assert_eq!("one,two", srs.get(|user| user.0.join(",")));
+}
+
+#[test]
+fn test_srs_thin() {
+    use gsrs::SRSThin;
+    let mut thin = std::pin::pin!(SRSThin::<_, Words>::new(*b"a b"));
+    let addr = thin.owner() as *const [u8; 3];
+    let first = thin.as_mut().with(|user, owner| {
+        user.0 = std::str::from_utf8(owner).unwrap().split(' ').collect();
+        user.0[0].as_ptr() as usize
+    });
+    assert_eq!(addr as usize, first);
+    assert_eq!(2, thin.as_mut().with(|user, _| user.0.len()));
+    assert_eq!(addr, thin.owner() as *const [u8; 3]);
+    assert_eq!("b", thin.get_ref(|user, _| user.0[1]));
+}
+
+#[test]
+fn test_send() {
+    let srs = words("one two");
+    let word = std::thread::spawn(move || srs.get_ref(|user, _| user.0[1]).to_owned())
+        .join()
+        .unwrap();
+    assert_eq!("two", word);
+}
+
+#[test]
+fn test_sync() {
+    let srs = words("one two");
+    let srs = &srs;
+    std::thread::scope(|scope| {
+        let first = scope.spawn(|| srs.get_ref(|user, _| user.0[0]));
+        let second = scope.spawn(|| srs.get_ref(|user, _| user.0[1]));
+        assert_eq!("one", first.join().unwrap());
+        assert_eq!("two", second.join().unwrap());
+    });
+}
+
+#[test]
+fn test_create_with_lifetime_casts() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("x yy zzz".to_owned(), |s| s.split(' ').collect());
+    assert_eq!(3, srs.get(|user| user.len()));
+    let owner_range = srs.as_ptr() as usize..srs.as_ptr() as usize + srs.len();
+    assert!(srs.get(move |user| user.iter().all(|it| owner_range.contains(&(it.as_ptr() as usize)))));
+
+    let srs = SRS::<_, &'static str>::create_with(Box::new("abc".to_owned()), |s| &s[1..]);
+    assert_eq!("bc", srs.get_ref(|user, _| *user));
+}
+
+#[test]
+fn test_into_owner() {
+    let mut srs = SRS::<_, Vec<&'static str>>::new("a b c".to_owned());
+    srs.with(|user, owner| user.extend(owner.split(' ')));
+    assert_eq!(3, srs.get(|user| user.len()));
+    let owner = srs.into_owner();
+    assert_eq!("a b c", owner);
+}
+
+#[test]
+fn test_map_owner() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("ab cd".to_owned(), |s| s.split(' ').collect());
+    let srs = srs.map_owner::<_, Vec<&'static char>, _, _>(|s| s.chars().collect::<Vec<_>>(), |chars| {
+        chars.iter().filter(|c| c.is_alphabetic()).collect()
+    });
+    assert_eq!(vec!['a', 'b', 'c', 'd'], srs.get(|user| user.iter().map(|c| **c).collect::<Vec<_>>()));
+    let owner_range = srs.as_ptr_range();
+    assert!(srs.get(move |user| user.iter().all(|c| owner_range.contains(&(*c as *const char)))));
+}
+
+#[test]
+fn test_with_owner_mut() {
+    let mut srs = SRS::<_, Vec<&'static str>>::create_with(vec!["a".to_owned()], |v| {
+        v.iter().map(|it| it.as_str()).collect()
+    });
+    unsafe {
+        srs.with_owner_mut(|owner, user| {
+            for i in 0..100 {
+                owner.push(i.to_string());
+            }
+            user.push(owner.last().unwrap());
+        });
+    }
+    assert_eq!(vec!["a", "99"], srs.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+}
+
+#[test]
+fn test_clone_with() {
+    fn build(s: &str) -> Vec<&str> {
+        s.split(' ').collect()
+    }
+    let srs = SRS::<String, Vec<&'static str>>::create_with("a b".to_owned(), |s| build(s));
+    let clone = srs.clone_with(|s| build(s));
+    assert_eq!(srs.get(|user| user.len()), clone.get(|user| user.len()));
+    let original = srs.as_bytes().as_ptr_range();
+    let cloned = clone.as_bytes().as_ptr_range();
+    assert!(clone.get(move |user| user.iter().all(|it| cloned.contains(&it.as_ptr()) && !original.contains(&it.as_ptr()))));
+}
+
+#[test]
+fn test_eq_by_owner() {
+    let a = SRS::<_, Vec<&'static str>>::create_with("a b".to_owned(), |s| s.split(' ').collect());
+    let b = SRS::<_, Vec<&'static str>>::create_with("a b".to_owned(), |s| vec![&s[..1]]);
+    let c = SRS::<_, Vec<&'static str>>::create_with("a c".to_owned(), |s| s.split(' ').collect());
+    assert!(a == b);
+    assert!(a != c);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_hash_by_owner() {
+    use std::collections::HashSet;
+
+    let set = ["a b", "a b", "c", "c", "a b"]
+        .iter()
+        .map(|s| SRS::<_, Vec<&'static str>>::create_with(s.to_string(), |s| s.split(' ').collect()))
+        .collect::<HashSet<_>>();
+    assert_eq!(2, set.len());
+    assert!(set.iter().any(|it| it.get(|user| user.len()) == 2));
+}
+
+#[test]
+fn test_ord_by_owner() {
+    use std::collections::BTreeSet;
+
+    let set = ["c d", "a", "b c", "a"]
+        .iter()
+        .map(|s| SRS::<_, Vec<&'static str>>::create_with(s.to_string(), |s| s.split(' ').collect()))
+        .collect::<BTreeSet<_>>();
+    let owners = set.iter().map(|it| it.as_str()).collect::<Vec<_>>();
+    assert_eq!(owners, ["a", "b c", "c d"]);
+    assert_eq!(set.iter().last().unwrap().get(|user| user.len()), 2);
+}
+
+#[test]
+fn test_drop_order() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static READ_ON_DROP: AtomicUsize = AtomicUsize::new(0);
+
+    struct Reader<'a>(&'a String);
+    deref_with_lifetime!(Reader);
+    impl Drop for Reader<'_> {
+        fn drop(&mut self) {
+            // would be use after free if owner was dropped first
+            READ_ON_DROP.store(self.0.len(), Ordering::SeqCst);
+        }
+    }
+
+    let srs = SRS::<String, Reader>::create_with("hello".to_owned(), Reader);
+    let moved = srs;
+    drop(moved);
+    assert_eq!(5, READ_ON_DROP.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_split_into_box() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("ab cd".to_owned(), |s| s.split(' ').collect());
+    let mut slot = None;
+    let (owner, refs) = srs.split_into_box(&mut slot);
+    assert_eq!("ab cd", owner);
+    assert_eq!(vec!["ab", "cd"], refs);
+    assert_eq!(owner.as_ptr(), refs[0].as_ptr());
+    drop(refs);
+    assert_eq!("ab cd", *slot.unwrap());
+}
+
+#[test]
+fn test_macro_where_clause() {
+    struct Sorted<'a, T: Ord>(&'a [T]);
+    deref_with_lifetime!(Sorted<'a, T> where T: Ord);
+
+    impl<T: Ord> Sorted<'_, T> {
+        fn contains(&self, item: &T) -> bool {
+            self.0.binary_search(item).is_ok()
+        }
+    }
+
+    let srs = SRS::<Vec<u32>, Sorted<u32>>::create_with(vec![1, 3, 5], |v| Sorted(v));
+    assert!(srs.get(|user| user.contains(&3)));
+    assert!(!srs.get(|user| user.contains(&4)));
+}
+
+#[test]
+fn test_macro_list() {
+    struct Names<'a>(Vec<&'a str>);
+    struct Bytes<'a>(&'a [u8]);
+    struct First<'a>(Option<&'a char>);
+    deref_with_lifetime!(Names, Bytes, First,);
+
+    let names = SRS::<String, Names>::create_with("a b".to_owned(), |s| Names(s.split(' ').collect()));
+    let bytes = SRS::<Vec<u8>, Bytes>::create_with(vec![1, 2, 3], |v| Bytes(&v[1..]));
+    let first = SRS::<Vec<char>, First>::create_with(vec!['x'], |v| First(v.first()));
+    assert_eq!(2, names.get(|user| user.0.len()));
+    assert_eq!(vec![2, 3], bytes.get(|user| user.0.to_vec()));
+    assert_eq!(Some('x'), first.get(|user| user.0.copied()));
+}
+
+#[test]
+fn test_pinned() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("a b".to_owned(), |s| s.split(' ').collect());
+    let mut pinned = srs.pinned();
+    pinned.as_mut().with_pinned(|user, owner| user.push(&owner[..1]));
+    assert_eq!("b", pinned.as_ref().get_ref_pinned(|user, _| user[1]));
+    assert_eq!(3, pinned.get(|user| user.len()));
+}
+
+#[test]
+fn test_from_boxed_dyn() {
+    let owner: Box<dyn AsRef<str>> = Box::new("dyn owner".to_owned());
+    let srs = SRS::<dyn AsRef<str>, Vec<&'static str>>::from_boxed_dyn(owner, |s| s.as_ref().split(' ').collect());
+    assert_eq!("owner", srs.get_ref(|user, _| user[1]));
+    assert_eq!((*srs).as_ref().as_ptr(), srs.get_ref(|user, _| user[0]).as_ptr());
+    let moved = Box::new(srs);
+    assert_eq!(2, moved.get(|user| user.len()));
+}
+
+#[test]
+fn test_replace_owner() {
+    let words: fn(&String) -> Vec<&str> = |s| s.split(' ').collect();
+    let mut srs = SRS::<String, Vec<&'static str>>::create_with("old config".to_owned(), words);
+    let allocation = &*srs as *const String;
+    srs.replace_owner("new config values".to_owned(), words);
+    assert_eq!(allocation, &*srs as *const String);
+    assert_eq!("new config values", *srs);
+    assert_eq!(vec!["new", "config", "values"], srs.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_borrow_owner_key() {
+    use std::collections::HashSet;
+
+    let words: fn(&String) -> Vec<&str> = |s| s.split(' ').collect();
+    let mut set = HashSet::new();
+    set.insert(SRS::<String, Vec<&'static str>>::create_with("a b".to_owned(), words));
+    set.insert(SRS::<String, Vec<&'static str>>::create_with("c".to_owned(), words));
+    let (key, missing) = (String::from("a b"), String::from("a"));
+    let found = set.get(&key).unwrap();
+    assert_eq!(2, found.get(|user| user.len()));
+    assert!(!set.contains(&missing));
+
+    fn owner_len<T: AsRef<String>>(it: &T) -> usize {
+        it.as_ref().len()
+    }
+    assert_eq!(3, owner_len(found));
+}
+
+#[test]
+fn test_get_ref_mut() {
+    let mut srs = SRS::<(String, Vec<u32>), Vec<&'static str>>::create_with(("a b".to_owned(), vec![0, 0]), |owner| {
+        owner.0.split(' ').collect()
+    });
+    let counter = unsafe { srs.get_ref_mut(|user, owner| &mut owner.1[user.len() - 1]) };
+    *counter += 5;
+    assert_eq!(vec![0, 5], srs.1);
+    assert_eq!(vec!["a", "b"], srs.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+}
+
+#[test]
+fn test_try_with() {
+    let mut srs = SRS::<_, Vec<&'static str>>::new("1 2 x".to_owned());
+    let parsed = srs.try_with(|user, owner| {
+        let first = owner.split(' ').next().unwrap();
+        user.push(first);
+        first.parse::<u32>()
+    });
+    assert_eq!(Ok(1), parsed);
+    let failed = srs.try_with(|user, owner| {
+        let last = owner.rsplit(' ').next().unwrap();
+        user.push(last);
+        last.parse::<u32>().map_err(|err| err.to_string())
+    });
+    assert!(failed.is_err());
+    assert_eq!(vec!["1", "x"], srs.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+}
+
+#[test]
+fn test_get_ref2() {
+    struct Entry<'a>(&'a str, &'a [u8]);
+    deref_with_lifetime!(Entry);
+
+    let srs = SRS::<_, Entry>::create_with(("name".to_owned(), vec![1u8, 2, 3]), |(key, value)| {
+        Entry(key, &value[1..])
+    });
+    let (key, value) = srs.get_ref2(|user, _| (user.0, user.1));
+    assert_eq!("name", key);
+    assert_eq!(&[2, 3], value);
+}
+
+#[test]
+fn test_from_parts() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("a b".to_owned(), |s| s.split(' ').collect());
+    let mut slot = None;
+    let (_, refs) = srs.split_into_box(&mut slot);
+    let refs = refs.into_iter().map(|it| it as *const str).collect::<Vec<_>>();
+    let owner = slot.take().unwrap();
+    let srs = unsafe { SRS::<String, Vec<&'static str>>::from_parts(owner, refs.into_iter().map(|it| &*it).collect()) };
+    let moved = Box::new(srs);
+    assert_eq!("b", moved.get_ref(|user, _| user[1]));
+}
+
+#[test]
+fn test_map_user() {
+    let mut srs = SRS::<_, Vec<&'static str>>::create_with("a long list of words".to_owned(), |s| s.split(' ').collect());
+    srs.map_user(|user, _| user.into_iter().filter(|it| it.len() > 2).collect());
+    assert_eq!(vec!["long", "list", "words"], srs.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+    srs.map_user(|mut user, owner| {
+        user.push(&owner[..1]);
+        user
+    });
+    assert_eq!("a", srs.get_ref(|user, _| user[3]));
+}
+
+#[test]
+fn test_with_disjoint() {
+    let mut words = SRS::<_, Vec<&'static str>>::create_with("two four".to_owned(), |s| s.split(' ').collect());
+    let mut numbers = SRS::<_, Vec<&'static str>>::create_with("one=1 two=2 three=3 four=4".to_owned(), |_| vec![]);
+    let found = gsrs::with_disjoint(&mut words, &mut numbers, |words, _, numbers, owner| {
+        for word in words.iter() {
+            let entry = owner.split(' ').find(|it| it.split('=').next() == Some(word)).unwrap();
+            numbers.push(&entry[entry.len() - 1..]);
+        }
+        words.pop();
+        numbers.len()
+    });
+    assert_eq!(2, found);
+    assert_eq!(1, words.get(|user| user.len()));
+    assert_eq!(vec!["2", "4"], numbers.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_create_with_panic_drops_owner() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    struct Owner(String);
+    impl Drop for Owner {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let result = std::panic::catch_unwind(|| {
+        SRS::<Owner, Vec<&'static str>>::create_with(Owner("a b".to_owned()), |owner| {
+            let refs = owner.0.split(' ').collect::<Vec<_>>();
+            if refs.len() == 2 {
+                panic!("build failed");
+            }
+            refs
+        })
+    });
+    assert!(result.is_err());
+    assert!(DROPPED.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_owner_ptr_stable() {
+    let srs = SRS::<_, Vec<&'static str>>::create_with("ffi".to_owned(), |s| vec![s.as_str()]);
+    let ptr = srs.owner_ptr();
+    let mut moved = Vec::new();
+    moved.push(srs);
+    moved.reserve(100);
+    assert_eq!(ptr, moved[0].owner_ptr());
+    assert_eq!("ffi", unsafe { &*ptr });
+}
+
+#[test]
+fn test_debug_owner_only() {
+    struct NoDebug<'a>(Vec<&'a str>);
+    deref_with_lifetime!(NoDebug);
+
+    let srs = SRS::<_, NoDebug>::create_with("a b".to_owned(), |s| NoDebug(s.split(' ').collect()));
+    assert_eq!(r#"SRS { owner: "a b", .. }"#, format!("{:?}", srs));
+    assert_eq!(2, srs.get(|user| user.0.len()));
+}
+
+#[test]
+fn test_create_with_ctx() {
+    struct Config<'a> {
+        separator: &'a str,
+        skip_empty: bool,
+    }
+
+    let separator = String::from(",");
+    let config = Config {
+        separator: &separator,
+        skip_empty: true,
+    };
+    let mut srs = SRS::<_, Vec<&'static str>>::create_with_ctx("a,,b,c".to_owned(), &config, |owner, config| {
+        owner
+            .split(config.separator)
+            .filter(|it| !config.skip_empty || !it.is_empty())
+            .collect()
+    });
+    assert!(srs.with(|user, _| *user == ["a", "b", "c"]));
+}
+
+#[test]
+fn test_take_and_set_user() {
+    let mut srs = SRS::<_, Vec<&'static str>>::create_with("a bb ccc".to_owned(), |s| s.split(' ').collect());
+    let base = srs.as_ptr() as usize;
+    let offsets = srs
+        .take_user()
+        .iter()
+        .map(|it| it.as_ptr() as usize - base)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [0, 2, 5]);
+    assert_eq!(srs.user_len(), 0);
+
+    srs.set_user(|s| s.split(' ').rev().collect());
+    assert!(srs.with(|user, _| *user == ["ccc", "bb", "a"]));
+}
+
+#[test]
+fn test_zero_sized_owner() {
+    for _ in 0..100 {
+        let mut srs = SRS::<(), Vec<&'static ()>>::new(());
+        srs.with(|user, owner| user.extend([owner, owner]));
+        assert_eq!(2, srs.user_len());
+        let moved = Box::new(srs);
+        assert!(std::ptr::eq(moved.get_ref(|user, _| user[0]), &**moved));
+    }
+}
+
+#[test]
+fn test_try_set_user() {
+    let mut srs = SRS::<_, Vec<&'static str>>::create_with("1 2 x".to_owned(), |s| s.split(' ').collect());
+
+    let numbers: Result<(), std::num::ParseIntError> = srs.try_set_user(|s| {
+        s.split(' ').map(|it| it.parse::<u32>().map(|_| it)).collect()
+    });
+    assert!(numbers.is_err());
+    assert!(srs.with(|user, _| *user == ["1", "2", "x"]));
+
+    let short: Result<(), &str> = srs.try_set_user(|s| Ok(s.split(' ').filter(|it| it.len() == 1).take(2).collect()));
+    assert!(short.is_ok());
+    assert!(srs.with(|user, _| *user == ["1", "2"]));
+}
+
+#[test]
+fn test_arc_srs() {
+    use gsrs::ArcSRS;
+    use std::sync::Arc;
+
+    let text: Arc<str> = Arc::from("one two\nthree");
+    let words = ArcSRS::<str, Vec<&'static str>>::from_arc(text.clone(), |s| s.split_whitespace().collect());
+    let lines = ArcSRS::<str, Vec<&'static str>>::from_arc(text.clone(), |s| s.lines().collect());
+    assert_eq!(3, Arc::strong_count(&text));
+    assert_eq!("three", words.get_ref(|user, _| user[2]));
+    assert_eq!("one two", lines.get_ref(|user, _| user[0]));
+    assert_eq!(words.get_ref(|user, _| user[2]).as_ptr(), lines.get_ref(|user, _| user[1]).as_ptr());
+
+    let mut moved = vec![words.clone()];
+    assert_eq!(4, Arc::strong_count(&text));
+    assert!(Arc::ptr_eq(moved[0].arc(), lines.arc()));
+    assert_eq!(3, moved[0].with(|user, _| user.len()));
+    drop(moved);
+    drop(words);
+    drop(lines);
+    assert_eq!(1, Arc::strong_count(&text));
+}
+
+#[test]
+fn test_with_owner_mut_fixed_vec() {
+    use gsrs::FixedVec;
+
+    let mut srs = SRS::<_, Vec<&'static [u8]>>::create_with(FixedVec::with_capacity(8), |_| Vec::new());
+    let base = srs.as_ptr();
+    unsafe {
+        srs.with_owner_mut(|owner, user| {
+            assert!(owner.extend_from_slice(b"ab"));
+            user.push(&owner[..]);
+        });
+        srs.with_owner_mut(|owner, user| {
+            let start = owner.len();
+            assert!(owner.extend_from_slice(b"cde"));
+            assert!(!owner.extend_from_slice(b"fghi"));
+            for &b in b"fgh" {
+                assert_eq!(Ok(()), owner.push(b));
+            }
+            assert_eq!(Err(b'i'), owner.push(b'i'));
+            let owner: &FixedVec<u8> = owner;
+            user.push(&owner[start..start + 3]);
+        });
+    }
+    assert_eq!(base, srs.as_ptr());
+    assert_eq!(8, srs.len());
+    assert!(srs.with(|user, _| *user == [&b"ab"[..], &b"cde"[..]]));
+}
+
+// code that should never be able to compile is checked in tests/ui/fail
 
 #[rustversion::since(1.36)]
 mod arena {
@@ -184,4 +1390,365 @@ mod arena {
 
         assert_eq!(b, 1);
     }
+
+    #[test]
+    #[cfg(feature = "arena")]
+    fn test_arena_push() {
+        use gsrs::arena::ArenaSRS;
+
+        let mut srs = ArenaSRS::<MyBigStruct, SRSUser<'static>>::default();
+        let pushed = srs.push(MyBigStruct { f1: 1, _f2: None }, |user, r| user.type1.push(r));
+        assert_eq!(1, pushed.f1);
+        srs.push(MyBigStruct { f1: 2, _f2: None }, |user, r| user.type1.push(r));
+
+        let b = test1(srs);
+        assert_eq!(b, 1);
+    }
+
+    #[test]
+    fn test_shared_owner_views() {
+        struct View<'a>(Vec<&'a MyBigStruct>);
+        deref_with_lifetime!(View);
+
+        let shared = SharedOwner::new(Arena::new());
+        let mut even = shared.view::<View, _>(|_| View(Vec::new()));
+        let mut odd = shared.view::<View, _>(|_| View(Vec::new()));
+        let all = shared.view::<View, _>(|arena| View(vec![arena.alloc(MyBigStruct { f1: 10, _f2: None })]));
+        for i in 0..4 {
+            let item = even.owner().alloc(MyBigStruct { f1: i, _f2: None });
+            if i % 2 == 0 {
+                even.user_mut().0.push(item);
+            } else {
+                odd.user_mut().0.push(item);
+            }
+        }
+        // views can be moved together while `shared` is alive
+        let views = [even, odd, all];
+        let sums = views
+            .iter()
+            .map(|view| view.user().0.iter().map(|it| it.f1).sum::<usize>())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![2, 4, 10], sums);
+    }
+
+    #[test]
+    fn test_collect_nodes() {
+        enum Node {
+            Ident(&'static str),
+            Number(i64),
+            Add,
+        }
+
+        // `a + 1 + b` in postfix order
+        let srs = SRS::<Arena<Node>, Vec<&Node>>::create_with(Arena::new(), |arena| {
+            vec![
+                &*arena.alloc(Node::Ident("a")),
+                &*arena.alloc(Node::Number(1)),
+                &*arena.alloc(Node::Add),
+                &*arena.alloc(Node::Ident("b")),
+                &*arena.alloc(Node::Add),
+            ]
+        });
+        let moved = Box::new(srs);
+        let idents = moved
+            .collect_nodes(|it| matches!(it, Node::Ident(_)))
+            .into_iter()
+            .map(|it| match it {
+                Node::Ident(name) => *name,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a", "b"], idents);
+        assert_eq!(1, moved.collect_nodes(|it| matches!(it, Node::Number(1))).len());
+    }
+
+    #[test]
+    fn test_macro_two_lifetimes() {
+        struct Refs<'a, 'b>(Vec<&'a str>, Vec<&'b [u8]>);
+        deref_with_lifetime!(Refs<'a, 'b>);
+
+        let mut srs = SRS::<(Arena<String>, Arena<Vec<u8>>), Refs>::create_with(
+            (Arena::new(), Arena::new()),
+            |(names, data)| Refs(vec![names.alloc("first".to_owned())], vec![data.alloc(vec![1, 2])]),
+        );
+        srs.with(|user, (names, data)| {
+            user.0.push(names.alloc("second".to_owned()));
+            user.1.push(data.alloc(vec![3]));
+        });
+        let moved = vec![srs];
+        let srs = moved.into_iter().next().unwrap();
+        let mut srs = Box::new(srs);
+        assert_eq!("first second", srs.with(|user, _| user.0.join(" ")));
+        assert_eq!(vec![1, 2, 3], srs.with(|user, _| user.1.concat()));
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn test_arena_heap_size() {
+        let srs = SRS::<Arena<u64>, Vec<&'static u64>>::create_with(Arena::new(), |arena| {
+            (0..3).map(|it| &*arena.alloc(it)).collect()
+        });
+        assert_eq!(24, srs.owner_heap_size());
+    }
+}
+
+#[cfg(feature = "futures")]
+mod stream {
+    use futures::stream;
+    use gsrs::*;
+
+    struct Chunks<'a>(Vec<&'a [u8]>);
+    deref_with_lifetime!(Chunks);
+
+    #[test]
+    fn test_create_with_async() {
+        let srs = SRS::<_, Chunks>::create_with_async(b"a bc".to_vec(), |owner| async move {
+            futures::future::ready(()).await;
+            Chunks(owner.split(|&b| b == b' ').collect())
+        });
+        let srs = futures::executor::block_on(srs);
+        assert_eq!(2, srs.get(|user| user.0[1].len()));
+    }
+
+    #[tokio::test]
+    async fn test_from_stream() {
+        let chunks = vec![b"hello as".to_vec(), b"ync ".to_vec(), b"world".to_vec()];
+        let srs = SRS::<_, Chunks>::from_stream(stream::iter(chunks), |owner| {
+            Chunks(owner.split(|&b| b == b' ').collect())
+        })
+        .await;
+        // moving is still fine after construction
+        let mut srs = Box::new(srs);
+        assert_eq!(b"async", srs.get_ref(|user, _| user.0[1]));
+        assert_eq!(3, srs.with(|user, _| user.0.len()));
+    }
+}
+
+#[cfg(feature = "stable_deref")]
+mod stable_deref {
+    use gsrs::SRS;
+    use stable_deref_trait::StableDeref;
+
+    fn owner_addr<T: StableDeref>(value: &T) -> *const T::Target {
+        &**value
+    }
+
+    #[test]
+    fn test_stable_deref() {
+        let srs = SRS::<_, &'static str>::create_with("owner".to_owned(), |owner| &owner[..]);
+        let addr = owner_addr(&srs);
+        let moved = Box::new(srs);
+        assert_eq!(addr, owner_addr(&*moved));
+    }
+}
+
+#[cfg(feature = "yoke")]
+mod yoke {
+    use gsrs::SRS;
+
+    #[test]
+    fn test_yoke_round_trip() {
+        let srs = SRS::<_, &'static str>::create_with("hello world".to_owned(), |owner| &owner[6..]);
+        let yoke = srs.into_yoke::<&'static str, _>(|owner| &owner[..5]);
+        assert_eq!("hello", *yoke.get());
+        let srs = SRS::<_, &'static str>::from_yoke(yoke, |owner| &owner[6..]);
+        let srs = Box::new(srs);
+        assert_eq!("world", srs.get_ref(|user, _| *user));
+        assert_eq!("hello world", srs.as_str());
+    }
+}
+
+#[cfg(feature = "leak")]
+mod leak {
+    use super::words;
+    use super::Words;
+    use std::sync::OnceLock;
+
+    static CACHE: OnceLock<(&'static String, &'static Words<'static>)> = OnceLock::new();
+
+    #[test]
+    fn test_into_static() {
+        let srs = words("one two");
+        CACHE.set(srs.into_static()).ok().unwrap();
+        let (owner, words) = CACHE.get().unwrap();
+        assert_eq!("one two", owner.as_str());
+        assert_eq!(vec!["one", "two"], words.0);
+    }
+}
+
+#[cfg(feature = "rayon")]
+mod rayon {
+    use super::Words;
+    use gsrs::*;
+    use std::ops::Range;
+
+    // words that start inside of the range
+    fn words_in(text: &String, range: Range<usize>) -> Vec<&str> {
+        let bytes = text.as_bytes();
+        let mut words = Vec::new();
+        let mut i = range.start;
+        while i < range.end {
+            if bytes[i] != b' ' && (i == 0 || bytes[i - 1] == b' ') {
+                let len = text[i..].find(' ').unwrap_or(text.len() - i);
+                words.push(&text[i..i + len]);
+                i += len;
+            } else {
+                i += 1;
+            }
+        }
+        words
+    }
+
+    // crossbeam-epoch used by rayon is not clean under stacked borrows
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_rebuild_par() {
+        let text = (0..10_000).map(|it| format!("w{}", it)).collect::<Vec<_>>().join(" ");
+        let mut srs = SRS::<_, Words>::new(text);
+        srs.rebuild_par(8, words_in, |parts, user| user.0 = parts.concat());
+        let expected = srs.as_str().split(' ').map(|it| it.to_owned()).collect::<Vec<_>>();
+        let words = srs.with(|user, _| user.0.iter().map(|it| it.to_string()).collect::<Vec<_>>());
+        assert_eq!(expected, words);
+    }
+}
+
+#[cfg(feature = "elsa")]
+mod elsa {
+    use elsa::FrozenVec;
+    use gsrs::*;
+
+    #[test]
+    fn test_from_iter_frozen() {
+        let mut srs = SRS::<FrozenVec<Box<String>>, Vec<&'static String>>::from_iter_frozen(
+            (0..3).map(|it| it.to_string()),
+            |items| items.iter().filter(|it| it.as_str() != "1").collect(),
+        );
+        srs.with(|user, items| user.push(items.push_get(Box::new("3".to_owned()))));
+        let srs = Box::new(srs);
+        assert_eq!(4, srs.len());
+        assert_eq!(vec!["0", "2", "3"], srs.collect_nodes(|_| true));
+    }
+}
+
+#[cfg(feature = "memmap2")]
+mod mmap {
+    use gsrs::*;
+    use memmap2::Mmap;
+    use std::fs::File;
+
+    #[test]
+    fn test_from_shared_mmap() {
+        let path = std::env::temp_dir().join(format!("gsrs-mmap-{}", std::process::id()));
+        std::fs::write(&path, "index: one two").unwrap();
+        let map = || unsafe { Mmap::map(&File::open(&path).unwrap()).unwrap() };
+        let offsets = [(7, 3), (11, 3)];
+
+        // two mappings of the same file have different addresses, as in different processes
+        let first = SRS::from_shared_mmap(map(), &offsets).unwrap();
+        let second = SRS::from_shared_mmap(map(), &offsets).unwrap();
+        assert_ne!(first.as_ptr(), second.as_ptr());
+        for srs in [first, second] {
+            let table = srs.export_ffi_table(|map| (map.as_ptr(), map.len()), |user| user);
+            assert_eq!(vec![[7, 3], [11, 3]], table);
+            assert_eq!("two", &srs[1]);
+        }
+        assert!(SRS::from_shared_mmap(map(), &[(12, 10)]).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_support {
+    use gsrs::*;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let words: fn(&String) -> Vec<&str> = |s| s.split(' ').collect();
+        let srs = SRS::<String, Vec<&'static str>>::create_with("a bc".to_owned(), words);
+        let json = serde_json::to_string(&srs).unwrap();
+        assert_eq!("\"a bc\"", json);
+
+        let mut de = serde_json::Deserializer::from_str(&json);
+        let back = SRS::<String, Vec<&'static str>>::deserialize_with(&mut de, words).unwrap();
+        assert!(srs == back);
+        assert_eq!(vec!["a", "bc"], back.get(|user| user.iter().map(|it| it.to_string()).collect::<Vec<_>>()));
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+mod allocator {
+    use gsrs::*;
+    use std::alloc::{AllocError, Allocator, Global, Layout};
+    use std::ptr::NonNull;
+    use std::sync::atomic::{AtomicIsize, Ordering};
+
+    static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+    struct Counting;
+
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            LIVE.fetch_add(1, Ordering::SeqCst);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            LIVE.fetch_sub(1, Ordering::SeqCst);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_new_in() {
+        let mut srs = SRS::<String, Vec<&'static str>>::new_in("a b".to_owned(), Counting);
+        assert_eq!(1, LIVE.load(Ordering::SeqCst));
+        srs.with(|user, owner| user.extend(owner.split(' ')));
+        let moved = vec![srs];
+        assert_eq!("b", moved[0].get_ref(|user, _| user[1]));
+        drop(moved);
+        assert_eq!(0, LIVE.load(Ordering::SeqCst));
+
+        let srs = SRS::<String, Vec<&'static str>>::new_in("owner".to_owned(), Counting);
+        assert_eq!("owner", srs.into_owner());
+        assert_eq!(0, LIVE.load(Ordering::SeqCst));
+    }
+
+    // `AliasedBox` is larger than `Box` with this feature, so only pointer must be moved into slot
+    #[test]
+    fn test_split_into_box() {
+        let srs = SRS::<_, Vec<&'static str>>::create_with("a b".to_owned(), |s| s.split(' ').collect());
+        let mut slot = Some(Box::new("old".to_owned()));
+        let (owner, refs) = srs.split_into_box(&mut slot);
+        assert_eq!(("a b", vec!["a", "b"]), (owner.as_str(), refs));
+        assert_eq!("a b", slot.unwrap().as_str());
+    }
+
+    #[test]
+    #[should_panic(expected = "owner allocated with `new_in` can't be moved into `Box`")]
+    fn test_split_new_in_panics() {
+        let srs = SRS::<String, Vec<&'static str>>::new_in("a".to_owned(), Global);
+        let mut slot = None;
+        let _ = srs.split_into_box(&mut slot);
+    }
+
+    // not zero-sized itself, so owner can't be skipped just because whole block is empty
+    struct NoAlloc(#[allow(dead_code)] usize);
+
+    unsafe impl Allocator for NoAlloc {
+        fn allocate(&self, _: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            panic!("zero-sized owner should not be allocated")
+        }
+
+        unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+            panic!("zero-sized owner should not be deallocated")
+        }
+    }
+
+    #[test]
+    fn test_new_in_zero_sized() {
+        let mut srs = SRS::<(), Vec<&'static ()>>::new_in((), NoAlloc(0));
+        srs.with(|user, owner| user.push(owner));
+        assert_eq!(1, srs.user_len());
+        assert_eq!((), srs.into_owner());
+    }
 }
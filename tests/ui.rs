@@ -0,0 +1,17 @@
+// trybuild runs compiler, which is not supported under miri
+#[test]
+#[cfg_attr(miri, ignore)]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[cfg(feature = "derive")]
+fn ui_derive() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/derive/pass/*.rs");
+    t.compile_fail("tests/ui/derive/fail/*.rs");
+}
@@ -0,0 +1,18 @@
+use gsrs::DerefWithLifetime;
+use std::borrow::Cow;
+
+#[derive(DerefWithLifetime)]
+struct Owned<'a> {
+    refs: Vec<&'a str>,
+    cow: Cow<'a, str>,
+}
+
+#[derive(DerefWithLifetime)]
+struct TwoLifetimes<'a, 'b>(&'a str, &'b str);
+
+#[derive(DerefWithLifetime)]
+enum NotStruct<'a> {
+    A(&'a str),
+}
+
+fn main() {}
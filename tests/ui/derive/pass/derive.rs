@@ -0,0 +1,23 @@
+use gsrs::{DerefWithLifetime, SRS};
+
+#[derive(DerefWithLifetime)]
+struct Words<'a> {
+    words: Vec<&'a str>,
+    first: Option<&'a str>,
+    count: usize,
+}
+
+#[derive(DerefWithLifetime)]
+struct Pairs<'a, T: Clone>(Vec<(&'a T, &'a T)>);
+
+fn main() {
+    let srs = SRS::<String, Words>::create_with("a b c".to_owned(), |s| {
+        let words = s.split(' ').collect::<Vec<_>>();
+        Words { first: words.first().copied(), count: words.len(), words }
+    });
+    assert_eq!(Some("a"), srs.get(|user| user.first.map(str::to_owned)).as_deref());
+    assert_eq!(3, srs.get(|user| user.words.len() + user.count - 3));
+
+    let srs = SRS::<Vec<u8>, Pairs<u8>>::create_with(vec![1, 2], |v| Pairs(vec![(&v[0], &v[1])]));
+    assert_eq!((1, 2), srs.get(|user| (*user.0[0].0, *user.0[0].1)));
+}
@@ -0,0 +1,6 @@
+use gsrs::*;
+
+fn main() {
+    let ctx = String::from("ctx");
+    let _srs = SRS::<String, Vec<&'static str>>::create_with_ctx("a".to_owned(), &ctx, |_, ctx| vec![ctx.as_str()]);
+}
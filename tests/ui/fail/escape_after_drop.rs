@@ -0,0 +1,16 @@
+use gsrs::*;
+
+struct Test {
+    field: usize,
+}
+#[derive(Default)]
+struct TestRef<'a>(Option<&'a Test>);
+deref_with_lifetime!(TestRef);
+
+fn main() {
+    let mut srs = SRS::<Test, TestRef<'static>>::new(Test { field: 5 });
+    srs.with(|user, owner| *user = TestRef(Some(owner)));
+    let r = srs.get_ref(|user, _| user.0.unwrap());
+    drop(srs);
+    println!("{}", r.field);
+}
@@ -0,0 +1,15 @@
+use gsrs::*;
+use std::cell::Cell;
+
+#[derive(Default)]
+struct User<'a>(Cell<Option<&'a User<'a>>>);
+deref_with_lifetime!(User);
+
+fn main() {
+    let srs = SRS::<(), User>::default();
+    // reference to the user itself would dangle after `srs` is moved
+    let _ = srs.get_ref_async(|user, _| {
+        user.0.set(Some(user));
+        async {}
+    });
+}
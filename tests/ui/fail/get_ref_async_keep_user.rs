@@ -0,0 +1,10 @@
+use gsrs::*;
+
+struct Words<'a>(Vec<&'a str>);
+deref_with_lifetime!(Words);
+
+fn main() {
+    let srs = SRS::<_, Words>::create_with("a b".to_owned(), |owner| Words(owner.split(' ').collect()));
+    // future must not keep referencing part itself
+    let _ = srs.get_ref_async(|user, _| async move { user.0.len() });
+}
@@ -0,0 +1,12 @@
+use gsrs::*;
+use std::cell::Cell;
+
+#[derive(Default)]
+struct User<'a>(Cell<Option<&'a User<'a>>>);
+deref_with_lifetime!(User);
+
+fn main() {
+    let srs = SRS::<(), User>::default();
+    // reference to the user itself would dangle after `srs` is moved
+    srs.refs(|guard| guard.user().0.set(Some(guard.user())));
+}
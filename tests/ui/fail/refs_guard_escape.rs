@@ -0,0 +1,12 @@
+use gsrs::*;
+
+struct Words<'a>(Vec<&'a str>);
+deref_with_lifetime!(Words);
+
+fn main() {
+    let srs = SRS::<_, Words>::create_with("a b".to_owned(), |owner| Words(owner.split(' ').collect()));
+    // guard must not outlive the closure
+    let word = srs.refs(|guard| guard.user().0[0]);
+    drop(srs);
+    println!("{}", word);
+}
@@ -0,0 +1,13 @@
+use gsrs::*;
+use std::cell::Cell;
+
+struct CellRef<'a>(Cell<&'a str>);
+deref_with_lifetime!(CellRef);
+
+fn require_sync<T: Sync>(_: &T) {}
+
+fn main() {
+    let srs = SRS::<_, CellRef>::create_with("a".to_owned(), |owner| CellRef(Cell::new(owner)));
+    // interior mutability in referencing part must not be shared between threads
+    require_sync(&srs);
+}
@@ -0,0 +1,15 @@
+use gsrs::*;
+
+struct Ref<'a>(&'a u8);
+deref_with_lifetime!(Ref);
+
+// owner must not be contravariant
+fn extend_owner<'a>(srs: SRS<&'a str, Ref<'static>>) -> SRS<&'static str, Ref<'static>> {
+    srs
+}
+
+fn main() {
+    let owner = "owner".to_owned();
+    let srs = SRS::<_, Ref>::create_with(owner.as_str(), |_| Ref(&0));
+    let _ = extend_owner(srs);
+}
@@ -0,0 +1,7 @@
+use gsrs::*;
+
+fn main() {
+    let mut a = SRS::<String, Vec<&'static str>>::new("a".to_owned());
+    let mut b = SRS::<String, Vec<&'static str>>::new("b".to_owned());
+    with_disjoint(&mut a, &mut b, |user_a, _, _, owner_b| user_a.push(owner_b));
+}
@@ -0,0 +1,15 @@
+use gsrs::*;
+
+struct Test {
+    field: usize,
+}
+struct TestRef<'a>(&'a Test);
+deref_with_lifetime!(TestRef);
+
+fn main() {
+    let mut srs = SRS::<Test, TestRef<'static>>::create_with(Test { field: 5 }, |owner| TestRef(owner));
+    let r = srs.with(|user, _| TestRef(user.0));
+    let mut ow = Box::new(Test { field: 0 });
+    let _ = srs.split(&mut ow);
+    println!("{}", r.0.field);
+}
@@ -0,0 +1,24 @@
+use gsrs::*;
+
+struct Ref<'a>(&'a u8);
+deref_with_lifetime!(Ref);
+
+// owner is covariant
+fn shorten_owner<'a>(srs: SRS<&'static str, Ref<'static>>) -> SRS<&'a str, Ref<'static>> {
+    srs
+}
+
+// user is covariant
+fn shorten_user<'a>(srs: SRS<u8, Ref<'static>>) -> SRS<u8, Ref<'a>> {
+    srs
+}
+
+fn main() {
+    let srs = SRS::<_, Ref>::create_with("owner", |_| Ref(&0));
+    let srs = shorten_owner(srs);
+    assert_eq!("owner", *srs);
+
+    let srs = SRS::<_, Ref>::create_with(5, Ref);
+    let srs = shorten_user(srs);
+    assert_eq!(5, *srs.get_ref(|user, _| user.0));
+}